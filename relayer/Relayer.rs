@@ -0,0 +1,699 @@
+// Relayer that submits PostContract and MessagingContract calls from its own
+// account, so the fee-paying source account never points back at the user.
+//
+// Clients build the host function, sign any Soroban auth entries it needs with
+// their own key and POST both to /relay. A fee bump would not help here: the
+// inner transaction's source account would still be the user's. Instead the
+// relayer builds a fresh transaction with itself as the source.
+
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use stellar_xdr::curr::{
+    AccountId, DecoratedSignature, Hash, HostFunction, InvokeContractArgs, InvokeHostFunctionOp, LedgerEntryData,
+    LedgerKey, LedgerKeyAccount, Limits, Memo, MuxedAccount, Operation, OperationBody, Preconditions, PublicKey,
+    ReadXdr, ScAddress, ScSymbol, ScVal, SequenceNumber, Signature, SignatureHint, SorobanAuthorizationEntry,
+    SorobanAuthorizedFunction, SorobanCredentials, SorobanTransactionData, Transaction, TransactionEnvelope,
+    TransactionExt, TransactionSignaturePayload, TransactionSignaturePayloadTaggedTransaction, TransactionV1Envelope,
+    Uint256, WriteXdr,
+};
+
+// Length of the rolling window relay limits are counted over
+const RATE_WINDOW: u64 = 60 * 60;
+
+// Furthest in the future a signed request may expire, bounding how long it is remembered
+const MAX_REQUEST_TTL: u64 = 5 * 60;
+
+// How long a connection may sit idle while reading a request or writing a response
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Largest request body the HTTP API accepts
+const MAX_BODY: usize = 64 * 1024;
+
+// Calls the relayer is willing to pay for. Anonymous posts, comments and reactions go through the
+// calls that take no address, so nothing on-chain ties them to the requester.
+const POST_FUNCTIONS: [&str; 23] = [
+    "create_post",
+    "create_anonymous_post",
+    "create_scoped_post",
+    "repost",
+    "quote_post",
+    "create_poll",
+    "vote_poll",
+    "commit_poll_vote",
+    "reveal_poll_vote",
+    "edit_post",
+    "set_post_expiry",
+    "add_comment",
+    "add_anonymous_comment",
+    "like_post",
+    "unlike_post",
+    "react",
+    "unreact",
+    "react_anonymously",
+    "unreact_anonymously",
+    "delete_post",
+    "delete_comment",
+    "report",
+    "appeal",
+];
+const MESSAGING_FUNCTIONS: [&str; 1] = ["send_message"];
+
+#[derive(Debug, PartialEq)]
+pub enum RelayError {
+    BadRequest(String),
+    NotAllowed(String),
+    RateLimited,
+    Rpc(String),
+}
+
+impl RelayError {
+    fn status(&self) -> &'static str {
+        match self {
+            RelayError::BadRequest(_) => "400 Bad Request",
+            RelayError::NotAllowed(_) => "403 Forbidden",
+            RelayError::RateLimited => "429 Too Many Requests",
+            RelayError::Rpc(_) => "502 Bad Gateway",
+        }
+    }
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayError::BadRequest(reason) => write!(f, "bad request: {reason}"),
+            RelayError::NotAllowed(reason) => write!(f, "not allowed: {reason}"),
+            RelayError::RateLimited => write!(f, "relay limit reached, try again later"),
+            RelayError::Rpc(reason) => write!(f, "rpc error: {reason}"),
+        }
+    }
+}
+
+fn bad_request(reason: impl fmt::Display) -> RelayError {
+    RelayError::BadRequest(reason.to_string())
+}
+
+fn rpc_error(reason: impl fmt::Display) -> RelayError {
+    RelayError::Rpc(reason.to_string())
+}
+
+pub struct Config {
+    pub network_passphrase: String,
+    pub secret_key: [u8; 32],
+    // (contract id, function name) pairs the relayer pays for
+    pub allowed_calls: Vec<([u8; 32], String)>,
+    // Only requesters with a UserProfileContract profile are relayed for, when set
+    pub profile_contract: Option<[u8; 32]>,
+    // Requesters with this PremiumContract feature get `premium_limit` instead of `free_limit`
+    pub premium_contract: Option<[u8; 32]>,
+    pub premium_feature: String,
+    pub free_limit: usize,
+    pub premium_limit: usize,
+    pub base_fee: u32,
+    // Simulations asking for more than this are refused rather than paid
+    pub max_resource_fee: i64,
+}
+
+impl Config {
+    pub fn allow_contract(&mut self, contract: [u8; 32], functions: &[&str]) {
+        for function in functions {
+            self.allowed_calls.push((contract, function.to_string()));
+        }
+    }
+
+    pub fn from_env() -> Result<Config, String> {
+        let var = |name: &str| std::env::var(name).map_err(|_| format!("{name} is not set"));
+        let contract = |name: &str| {
+            var(name).and_then(|id| {
+                stellar_strkey::Contract::from_string(&id)
+                    .map(|c| c.0)
+                    .map_err(|_| format!("{name} is not a contract id"))
+            })
+        };
+        let number = |name: &str, default: u64| {
+            std::env::var(name).map_or(Ok(default), |v| v.parse().map_err(|_| format!("{name} is not a number")))
+        };
+
+        let secret = stellar_strkey::ed25519::PrivateKey::from_string(&var("RELAYER_SECRET")?)
+            .map_err(|_| "RELAYER_SECRET is not a secret key".to_string())?;
+        let optional_contract = |name: &str| match std::env::var(name) {
+            Ok(_) => contract(name).map(Some),
+            Err(_) => Ok(None),
+        };
+        let mut config = Config {
+            network_passphrase: std::env::var("RELAYER_NETWORK_PASSPHRASE")
+                .unwrap_or("Standalone Network ; February 2017".to_string()),
+            secret_key: secret.0,
+            allowed_calls: Vec::new(),
+            profile_contract: optional_contract("RELAYER_PROFILE_CONTRACT")?,
+            premium_contract: optional_contract("RELAYER_PREMIUM_CONTRACT")?,
+            premium_feature: std::env::var("RELAYER_PREMIUM_FEATURE").unwrap_or("unlimited_messages".to_string()),
+            free_limit: number("RELAYER_FREE_LIMIT", 10)? as usize,
+            premium_limit: number("RELAYER_PREMIUM_LIMIT", 100)? as usize,
+            base_fee: number("RELAYER_BASE_FEE", 100)? as u32,
+            max_resource_fee: number("RELAYER_MAX_RESOURCE_FEE", 10_000_000)? as i64,
+        };
+        config.allow_contract(contract("RELAYER_POST_CONTRACT")?, &POST_FUNCTIONS);
+        config.allow_contract(contract("RELAYER_MESSAGING_CONTRACT")?, &MESSAGING_FUNCTIONS);
+        Ok(config)
+    }
+}
+
+pub struct Simulation {
+    pub transaction_data: SorobanTransactionData,
+    pub min_resource_fee: i64,
+    pub result: Option<ScVal>,
+}
+
+// The subset of the Soroban RPC API the relayer needs
+pub trait Rpc: Send + Sync {
+    fn account_sequence(&self, account: &AccountId) -> Result<i64, RelayError>;
+    fn simulate(&self, envelope: &TransactionEnvelope) -> Result<Simulation, RelayError>;
+    // Returns the transaction hash once the RPC has accepted it
+    fn send(&self, envelope: &TransactionEnvelope) -> Result<String, RelayError>;
+}
+
+// JSON-RPC client for a Soroban RPC server reachable over plain HTTP
+pub struct HttpRpc {
+    url: String,
+}
+
+impl HttpRpc {
+    pub fn new(url: &str) -> Self {
+        HttpRpc { url: url.to_string() }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, RelayError> {
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let response: Value = serde_json::from_str(&http_post(&self.url, &request.to_string())?).map_err(rpc_error)?;
+        if let Some(error) = response.get("error") {
+            return Err(rpc_error(format!("{method} failed: {error}")));
+        }
+        response.get("result").cloned().ok_or_else(|| rpc_error(format!("{method} returned no result")))
+    }
+}
+
+impl Rpc for HttpRpc {
+    fn account_sequence(&self, account: &AccountId) -> Result<i64, RelayError> {
+        let key = LedgerKey::Account(LedgerKeyAccount { account_id: account.clone() });
+        let result = self.call("getLedgerEntries", json!({"keys": [to_base64(&key)?]}))?;
+        let entry = result["entries"][0]["xdr"].as_str().ok_or_else(|| rpc_error("relayer account not found"))?;
+        match LedgerEntryData::from_xdr_base64(entry, Limits::none()).map_err(rpc_error)? {
+            LedgerEntryData::Account(account) => Ok(account.seq_num.0),
+            _ => Err(rpc_error("unexpected ledger entry for relayer account")),
+        }
+    }
+
+    fn simulate(&self, envelope: &TransactionEnvelope) -> Result<Simulation, RelayError> {
+        let result = self.call("simulateTransaction", json!({"transaction": to_base64(envelope)?}))?;
+        if let Some(error) = result.get("error") {
+            return Err(RelayError::NotAllowed(format!("simulation failed: {error}")));
+        }
+        let transaction_data = result["transactionData"].as_str().ok_or_else(|| rpc_error("no transactionData"))?;
+        let min_resource_fee = match &result["minResourceFee"] {
+            Value::String(fee) => fee.parse().map_err(rpc_error)?,
+            fee => fee.as_i64().ok_or_else(|| rpc_error("no minResourceFee"))?,
+        };
+        let result_xdr = result["results"][0]["xdr"].as_str();
+        Ok(Simulation {
+            transaction_data: SorobanTransactionData::from_xdr_base64(transaction_data, Limits::none())
+                .map_err(rpc_error)?,
+            min_resource_fee,
+            result: result_xdr.map(|xdr| ScVal::from_xdr_base64(xdr, Limits::none())).transpose().map_err(rpc_error)?,
+        })
+    }
+
+    fn send(&self, envelope: &TransactionEnvelope) -> Result<String, RelayError> {
+        let result = self.call("sendTransaction", json!({"transaction": to_base64(envelope)?}))?;
+        match result["status"].as_str() {
+            Some("PENDING") | Some("DUPLICATE") => {
+                result["hash"].as_str().map(str::to_string).ok_or_else(|| rpc_error("no transaction hash"))
+            }
+            status => Err(rpc_error(format!("transaction rejected with status {}", status.unwrap_or("unknown")))),
+        }
+    }
+}
+
+// A signed call submitted to /relay
+#[derive(Debug)]
+pub struct RelayRequest {
+    pub host_function: HostFunction,
+    pub auth: Vec<SorobanAuthorizationEntry>,
+    // Account that signed the request. It must match the call's auth entries, if any, and
+    // is only used for relay limits when the call needs no auth.
+    pub requester: [u8; 32],
+    // Lets a requester sign the same call twice; each signed request is relayed once
+    pub nonce: u64,
+    // Unix time after which the request is refused
+    pub expires_at: u64,
+    // Requester's ed25519 signature over `request_digest`
+    pub signature: [u8; 64],
+}
+
+impl RelayRequest {
+    // Expects {"host_function": base64, "auth": [base64], "requester": "G...", "nonce": u64, "expires_at": u64,
+    // "signature": hex}
+    pub fn from_json(body: &str) -> Result<Self, RelayError> {
+        let body: Value = serde_json::from_str(body).map_err(bad_request)?;
+        let field = |name: &str| body[name].as_str().ok_or_else(|| bad_request(format!("missing {name}")));
+
+        let host_function =
+            HostFunction::from_xdr_base64(field("host_function")?, Limits::none()).map_err(bad_request)?;
+        let mut auth = Vec::new();
+        for entry in body["auth"].as_array().unwrap_or(&Vec::new()) {
+            let entry = entry.as_str().ok_or_else(|| bad_request("auth entries must be base64 strings"))?;
+            auth.push(SorobanAuthorizationEntry::from_xdr_base64(entry, Limits::none()).map_err(bad_request)?);
+        }
+        let requester = stellar_strkey::ed25519::PublicKey::from_string(field("requester")?)
+            .map_err(|_| bad_request("requester is not an account id"))?
+            .0;
+        let number = |name: &str| body[name].as_u64().ok_or_else(|| bad_request(format!("missing {name}")));
+        let signature = decode_hex(field("signature")?)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| bad_request("signature must be 64 hex-encoded bytes"))?;
+        Ok(RelayRequest {
+            host_function,
+            auth,
+            requester,
+            nonce: number("nonce")?,
+            expires_at: number("expires_at")?,
+            signature,
+        })
+    }
+}
+
+// What the requester signs: sha256(network id || host function xdr || nonce || expires_at), integers big-endian
+pub fn request_digest(
+    network_id: &Hash,
+    host_function: &HostFunction,
+    nonce: u64,
+    expires_at: u64,
+) -> Result<[u8; 32], RelayError> {
+    let mut hasher = Sha256::new();
+    hasher.update(network_id.0);
+    hasher.update(host_function.to_xdr(Limits::none()).map_err(bad_request)?);
+    hasher.update(nonce.to_be_bytes());
+    hasher.update(expires_at.to_be_bytes());
+    Ok(hasher.finalize().into())
+}
+
+pub fn network_id(passphrase: &str) -> Hash {
+    Hash(Sha256::digest(passphrase.as_bytes()).into())
+}
+
+pub struct Relayer<R: Rpc> {
+    config: Config,
+    rpc: R,
+    signing_key: SigningKey,
+    network_id: Hash,
+    // Relay timestamps per identity, pruned to the rolling window
+    sends: Mutex<HashMap<ScAddress, Vec<u64>>>,
+    // Signatures of relayed requests with their expiry, pruned once expired
+    seen: Mutex<HashMap<[u8; 64], u64>>,
+    // Last sequence number sent. The ledger only catches up once a transaction is applied, so
+    // submissions count from whichever is higher; holding the lock serializes them.
+    last_sequence: Mutex<i64>,
+}
+
+impl<R: Rpc> Relayer<R> {
+    pub fn new(config: Config, rpc: R) -> Self {
+        Relayer {
+            signing_key: SigningKey::from_bytes(&config.secret_key),
+            network_id: network_id(&config.network_passphrase),
+            config,
+            rpc,
+            sends: Mutex::new(HashMap::new()),
+            seen: Mutex::new(HashMap::new()),
+            last_sequence: Mutex::new(0),
+        }
+    }
+
+    // The relayer's G... address, which needs XLM to pay fees
+    pub fn address(&self) -> String {
+        stellar_strkey::ed25519::PublicKey(self.signing_key.verifying_key().to_bytes()).to_string()
+    }
+
+    pub fn account_id(&self) -> AccountId {
+        account_id(self.signing_key.verifying_key().to_bytes())
+    }
+
+    // Check a request and submit it, returning the transaction hash
+    pub fn relay(&self, request: &RelayRequest, now: u64) -> Result<String, RelayError> {
+        self.check_signature(request)?;
+        if request.expires_at <= now || request.expires_at > now + MAX_REQUEST_TTL {
+            return Err(RelayError::NotAllowed("request is expired or expires too far ahead".to_string()));
+        }
+        let call = self.check_call(&request.host_function)?;
+        self.check_auth(call, &request.auth)?;
+        let identity = self.identity(request)?;
+        self.check_profile(&identity)?;
+        self.claim_request(request, now)?;
+        self.consume_quota(&identity, now)?;
+        self.submit(&request.host_function, &request.auth)
+    }
+
+    // Relays an identity can still make in the current rolling window
+    pub fn remaining_quota(&self, identity: &ScAddress, now: u64) -> Result<usize, RelayError> {
+        let limit = self.limit(identity)?;
+        Ok(limit.saturating_sub(self.recent_sends(identity, now).len()))
+    }
+
+    fn check_signature(&self, request: &RelayRequest) -> Result<(), RelayError> {
+        let key = VerifyingKey::from_bytes(&request.requester).map_err(bad_request)?;
+        let digest = request_digest(&self.network_id, &request.host_function, request.nonce, request.expires_at)?;
+        key.verify(&digest, &ed25519_dalek::Signature::from_bytes(&request.signature))
+            .map_err(|_| RelayError::NotAllowed("requester signature does not match".to_string()))
+    }
+
+    fn check_call<'a>(&self, host_function: &'a HostFunction) -> Result<&'a InvokeContractArgs, RelayError> {
+        let HostFunction::InvokeContract(call) = host_function else {
+            return Err(RelayError::NotAllowed("only contract calls are relayed".to_string()));
+        };
+        let allowed = match &call.contract_address {
+            ScAddress::Contract(Hash(contract)) => self.config.allowed_calls.iter().any(|(allowed, function)| {
+                allowed == contract && call.function_name.0.as_slice() == function.as_bytes()
+            }),
+            ScAddress::Account(_) => false,
+        };
+        if !allowed {
+            return Err(RelayError::NotAllowed("contract function is not relayed".to_string()));
+        }
+        Ok(call)
+    }
+
+    // Auth entries must be signed by their own addresses and authorize exactly this call.
+    // Signatures themselves are enforced when the transaction is simulated.
+    fn check_auth(&self, call: &InvokeContractArgs, auth: &[SorobanAuthorizationEntry]) -> Result<(), RelayError> {
+        for entry in auth {
+            if let SorobanCredentials::SourceAccount = entry.credentials {
+                return Err(RelayError::NotAllowed("source account auth would be signed by the relayer".to_string()));
+            }
+            match &entry.root_invocation.function {
+                SorobanAuthorizedFunction::ContractFn(authorized) if authorized == call => {}
+                _ => return Err(RelayError::NotAllowed("auth entry does not match the call".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    // Refuse a signed request that was already relayed. Expired ones are refused anyway, so they are forgotten.
+    fn claim_request(&self, request: &RelayRequest, now: u64) -> Result<(), RelayError> {
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, expires_at| *expires_at > now);
+        if seen.insert(request.signature, request.expires_at).is_some() {
+            return Err(RelayError::NotAllowed("request was already relayed".to_string()));
+        }
+        Ok(())
+    }
+
+    // The address relay limits are counted against: the one authorizing the call, whose
+    // signatures the network checks, or the requester for calls that need no auth.
+    fn identity(&self, request: &RelayRequest) -> Result<ScAddress, RelayError> {
+        let requester = ScAddress::Account(account_id(request.requester));
+        let mut identity = None;
+        for entry in &request.auth {
+            if let SorobanCredentials::Address(credentials) = &entry.credentials {
+                if identity.as_ref().is_some_and(|address| *address != credentials.address) {
+                    return Err(RelayError::NotAllowed("auth entries must come from a single address".to_string()));
+                }
+                identity = Some(credentials.address.clone());
+            }
+        }
+        match identity {
+            Some(identity @ ScAddress::Account(_)) if identity != requester => {
+                Err(RelayError::NotAllowed("requester does not match the authorizing address".to_string()))
+            }
+            Some(identity) => Ok(identity),
+            None => Ok(requester),
+        }
+    }
+
+    // Registering a profile takes an on-chain transaction, so fresh keys cannot mint new relay limits
+    fn check_profile(&self, identity: &ScAddress) -> Result<(), RelayError> {
+        let Some(profile) = self.config.profile_contract else {
+            return Ok(());
+        };
+        match self.read(profile, "get_profile", vec![ScVal::Address(identity.clone())])? {
+            Some(ScVal::Void) | None => Err(RelayError::NotAllowed("requester has no profile".to_string())),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn limit(&self, identity: &ScAddress) -> Result<usize, RelayError> {
+        if self.has_premium(identity)? {
+            Ok(self.config.premium_limit)
+        } else {
+            Ok(self.config.free_limit)
+        }
+    }
+
+    fn has_premium(&self, identity: &ScAddress) -> Result<bool, RelayError> {
+        let Some(premium) = self.config.premium_contract else {
+            return Ok(false);
+        };
+        let args = vec![ScVal::Address(identity.clone()), ScVal::Symbol(symbol(&self.config.premium_feature)?)];
+        Ok(matches!(self.read(premium, "has_feature_access", args)?, Some(ScVal::Bool(true))))
+    }
+
+    // Simulate a read-only contract call and return its result
+    fn read(&self, contract: [u8; 32], function: &str, args: Vec<ScVal>) -> Result<Option<ScVal>, RelayError> {
+        let call = HostFunction::InvokeContract(InvokeContractArgs {
+            contract_address: ScAddress::Contract(Hash(contract)),
+            function_name: symbol(function)?,
+            args: args.try_into().map_err(bad_request)?,
+        });
+        // Never submitted, so the sequence number is irrelevant
+        let envelope = self.envelope(self.transaction(0, self.config.base_fee, &call, &[], TransactionExt::V0)?)?;
+        Ok(self.rpc.simulate(&envelope)?.result)
+    }
+
+    fn recent_sends(&self, identity: &ScAddress, now: u64) -> Vec<u64> {
+        let sends = self.sends.lock().unwrap();
+        let log = sends.get(identity).cloned().unwrap_or_default();
+        log.into_iter().filter(|sent_at| sent_at + RATE_WINDOW > now).collect()
+    }
+
+    // Counts every accepted attempt, including ones that later fail simulation
+    fn consume_quota(&self, identity: &ScAddress, now: u64) -> Result<(), RelayError> {
+        let limit = self.limit(identity)?;
+        // Checked and recorded under one lock so concurrent requests cannot share the last slot
+        let mut sends = self.sends.lock().unwrap();
+        let log = sends.entry(identity.clone()).or_default();
+        log.retain(|sent_at| sent_at + RATE_WINDOW > now);
+        if log.len() >= limit {
+            return Err(RelayError::RateLimited);
+        }
+        log.push(now);
+        Ok(())
+    }
+
+    fn submit(&self, host_function: &HostFunction, auth: &[SorobanAuthorizationEntry]) -> Result<String, RelayError> {
+        let mut last_sequence = self.last_sequence.lock().unwrap();
+        let sequence = self.rpc.account_sequence(&self.account_id())?.max(*last_sequence) + 1;
+
+        let draft = self.transaction(sequence, self.config.base_fee, host_function, auth, TransactionExt::V0)?;
+        let simulation = self.rpc.simulate(&self.envelope(draft)?)?;
+        if simulation.min_resource_fee > self.config.max_resource_fee {
+            return Err(RelayError::NotAllowed("call is too expensive to relay".to_string()));
+        }
+
+        let fee = i64::from(self.config.base_fee) + simulation.min_resource_fee;
+        let fee = u32::try_from(fee).map_err(|_| RelayError::NotAllowed("fee out of range".to_string()))?;
+        let transaction =
+            self.transaction(sequence, fee, host_function, auth, TransactionExt::V1(simulation.transaction_data))?;
+        let hash = self.rpc.send(&self.envelope(transaction)?)?;
+        *last_sequence = sequence;
+        Ok(hash)
+    }
+
+    fn transaction(
+        &self,
+        sequence: i64,
+        fee: u32,
+        host_function: &HostFunction,
+        auth: &[SorobanAuthorizationEntry],
+        ext: TransactionExt,
+    ) -> Result<Transaction, RelayError> {
+        let operation = Operation {
+            source_account: None,
+            body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+                host_function: host_function.clone(),
+                auth: auth.to_vec().try_into().map_err(bad_request)?,
+            }),
+        };
+        Ok(Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256(self.signing_key.verifying_key().to_bytes())),
+            fee,
+            seq_num: SequenceNumber(sequence),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: vec![operation].try_into().map_err(bad_request)?,
+            ext,
+        })
+    }
+
+    fn envelope(&self, transaction: Transaction) -> Result<TransactionEnvelope, RelayError> {
+        let payload = TransactionSignaturePayload {
+            network_id: self.network_id.clone(),
+            tagged_transaction: TransactionSignaturePayloadTaggedTransaction::Tx(transaction.clone()),
+        };
+        let hash = Sha256::digest(payload.to_xdr(Limits::none()).map_err(bad_request)?);
+        let public_key = self.signing_key.verifying_key().to_bytes();
+        let signature = DecoratedSignature {
+            hint: SignatureHint(public_key[28..].try_into().unwrap()),
+            signature: Signature(self.signing_key.sign(&hash).to_bytes().to_vec().try_into().map_err(bad_request)?),
+        };
+        Ok(TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx: transaction,
+            signatures: vec![signature].try_into().map_err(bad_request)?,
+        }))
+    }
+}
+
+fn account_id(public_key: [u8; 32]) -> AccountId {
+    AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(public_key)))
+}
+
+fn symbol(name: &str) -> Result<ScSymbol, RelayError> {
+    Ok(ScSymbol(name.try_into().map_err(bad_request)?))
+}
+
+fn to_base64(value: &impl WriteXdr) -> Result<String, RelayError> {
+    value.to_xdr_base64(Limits::none()).map_err(bad_request)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+// Accept connections until the listener fails, one thread per connection.
+// Timeouts keep slow or stalled clients from holding threads indefinitely.
+pub fn serve<R: Rpc + 'static>(listener: TcpListener, relayer: Arc<Relayer<R>>) {
+    for stream in listener.incoming().flatten() {
+        if stream.set_read_timeout(Some(IO_TIMEOUT)).is_err() || stream.set_write_timeout(Some(IO_TIMEOUT)).is_err() {
+            continue;
+        }
+        let relayer = relayer.clone();
+        thread::spawn(move || handle(stream, &relayer));
+    }
+}
+
+fn handle<R: Rpc>(mut stream: TcpStream, relayer: &Relayer<R>) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let result = match read_request(&mut stream) {
+        Ok((method, path, body)) if method == "POST" && path == "/relay" => {
+            RelayRequest::from_json(&body).and_then(|request| relayer.relay(&request, now))
+        }
+        Ok(_) => {
+            let _ = write_response(&mut stream, "404 Not Found", &json!({"error": "not found"}));
+            return;
+        }
+        Err(err) => Err(err),
+    };
+    let _ = match result {
+        Ok(hash) => write_response(&mut stream, "200 OK", &json!({"hash": hash})),
+        Err(err) => write_response(&mut stream, err.status(), &json!({"error": err.to_string()})),
+    };
+}
+
+// Returns (method, path, body) of an HTTP/1.1 request
+pub fn read_request(stream: &mut TcpStream) -> Result<(String, String, String), RelayError> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(bad_request)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string());
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(bad_request)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(bad_request)?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(bad_request("body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+    Ok((method, path, String::from_utf8(body).map_err(bad_request)?))
+}
+
+pub fn write_response(stream: &mut TcpStream, status: &str, body: &Value) -> std::io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+// Minimal HTTP/1.1 POST for http:// URLs, enough to talk to a local RPC node
+pub fn http_post(url: &str, body: &str) -> Result<String, RelayError> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| rpc_error("only http:// RPC urls are supported"))?;
+    let (host, path) = rest.split_once('/').map_or((rest, "/".to_string()), |(h, p)| (h, format!("/{p}")));
+
+    let mut stream = TcpStream::connect(host).map_err(rpc_error)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT)).map_err(rpc_error)?;
+    stream.set_write_timeout(Some(IO_TIMEOUT)).map_err(rpc_error)?;
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .map_err(rpc_error)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(rpc_error)?;
+
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| rpc_error("malformed http response"))?;
+    if !head.starts_with("HTTP/1.1 200") && !head.starts_with("HTTP/1.0 200") {
+        return Err(rpc_error(head.lines().next().unwrap_or_default()));
+    }
+    if head.to_ascii_lowercase().contains("transfer-encoding: chunked") {
+        return decode_chunked(body).ok_or_else(|| rpc_error("malformed chunked body"));
+    }
+    Ok(body.to_string())
+}
+
+fn decode_chunked(mut body: &str) -> Option<String> {
+    let mut decoded = String::new();
+    loop {
+        let (size, rest) = body.split_once("\r\n")?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.push_str(rest.get(..size)?);
+        body = rest.get(size + 2..)?;
+    }
+}
+
+fn main() {
+    let config = Config::from_env().unwrap_or_else(|err| panic!("invalid relayer config: {err}"));
+    let rpc_url = std::env::var("RELAYER_RPC_URL").unwrap_or("http://localhost:8000/soroban/rpc".to_string());
+    let listen = std::env::var("RELAYER_LISTEN").unwrap_or("127.0.0.1:8787".to_string());
+
+    let relayer = Arc::new(Relayer::new(config, HttpRpc::new(&rpc_url)));
+    let listener = TcpListener::bind(&listen).unwrap_or_else(|err| panic!("cannot listen on {listen}: {err}"));
+    println!("relaying from {} on {listen}", relayer.address());
+    serve(listener, relayer);
+}
+
+#[cfg(test)]
+#[path = "Relayer_test.rs"]
+mod tests;
@@ -0,0 +1,386 @@
+#![cfg(test)]
+
+use super::*;
+use stellar_xdr::curr::{
+    AccountEntry, AccountEntryExt, ExtensionPoint, LedgerFootprint, SorobanAddressCredentials,
+    SorobanAuthorizedInvocation, SorobanResources, String32, Thresholds,
+};
+
+const POST_CONTRACT: [u8; 32] = [1; 32];
+const MESSAGING_CONTRACT: [u8; 32] = [2; 32];
+const PREMIUM_CONTRACT: [u8; 32] = [3; 32];
+const PROFILE_CONTRACT: [u8; 32] = [6; 32];
+const RELAYER_SEQUENCE: i64 = 7;
+const RESOURCE_FEE: i64 = 500;
+
+// State behind the stand-in RPC
+#[derive(Default)]
+struct Ledger {
+    profiles: Vec<ScAddress>,
+    premium: Vec<[u8; 32]>,
+    sent: Vec<TransactionEnvelope>,
+}
+
+// Serves the Soroban RPC methods the relayer uses from an in-memory ledger and returns its url
+fn stand_in_rpc(ledger: Arc<Mutex<Ledger>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/soroban/rpc", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let (_, _, body) = read_request(&mut stream).unwrap();
+            let request: Value = serde_json::from_str(&body).unwrap();
+            let result = rpc_result(&ledger, request["method"].as_str().unwrap(), &request["params"]);
+            write_response(&mut stream, "200 OK", &json!({"jsonrpc": "2.0", "id": 1, "result": result})).unwrap();
+        }
+    });
+    url
+}
+
+fn rpc_result(ledger: &Mutex<Ledger>, method: &str, params: &Value) -> Value {
+    match method {
+        "getLedgerEntries" => {
+            let LedgerKey::Account(key) =
+                LedgerKey::from_xdr_base64(params["keys"][0].as_str().unwrap(), Limits::none()).unwrap()
+            else {
+                panic!("unexpected ledger key")
+            };
+            let account = LedgerEntryData::Account(AccountEntry {
+                account_id: key.account_id,
+                balance: 100_000_000,
+                seq_num: SequenceNumber(RELAYER_SEQUENCE),
+                num_sub_entries: 0,
+                inflation_dest: None,
+                flags: 0,
+                home_domain: String32(Default::default()),
+                thresholds: Thresholds([1, 0, 0, 0]),
+                signers: Default::default(),
+                ext: AccountEntryExt::V0,
+            });
+            json!({"entries": [{"xdr": to_base64(&account).unwrap()}]})
+        }
+        "simulateTransaction" => {
+            let TransactionEnvelope::Tx(envelope) =
+                TransactionEnvelope::from_xdr_base64(params["transaction"].as_str().unwrap(), Limits::none()).unwrap()
+            else {
+                panic!("unexpected envelope")
+            };
+            let OperationBody::InvokeHostFunction(op) = &envelope.tx.operations[0].body else {
+                panic!("unexpected operation")
+            };
+            let HostFunction::InvokeContract(call) = &op.host_function else { panic!("unexpected host function") };
+            let result = match (&call.function_name.0.to_utf8_string_lossy()[..], &call.args[0]) {
+                (
+                    "has_feature_access",
+                    ScVal::Address(ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(user)))),
+                ) => ScVal::Bool(ledger.lock().unwrap().premium.contains(&user.0)),
+                ("get_profile", ScVal::Address(user)) if ledger.lock().unwrap().profiles.contains(user) => {
+                    ScVal::Map(None)
+                }
+                _ => ScVal::Void,
+            };
+            let data = SorobanTransactionData {
+                ext: ExtensionPoint::V0,
+                resources: SorobanResources {
+                    footprint: LedgerFootprint { read_only: Default::default(), read_write: Default::default() },
+                    instructions: 1_000,
+                    read_bytes: 100,
+                    write_bytes: 100,
+                },
+                resource_fee: RESOURCE_FEE,
+            };
+            json!({
+                "transactionData": to_base64(&data).unwrap(),
+                "minResourceFee": RESOURCE_FEE.to_string(),
+                "results": [{"xdr": to_base64(&result).unwrap()}],
+            })
+        }
+        "sendTransaction" => {
+            let envelope =
+                TransactionEnvelope::from_xdr_base64(params["transaction"].as_str().unwrap(), Limits::none()).unwrap();
+            let mut ledger = ledger.lock().unwrap();
+            ledger.sent.push(envelope);
+            json!({"status": "PENDING", "hash": format!("{:064x}", ledger.sent.len())})
+        }
+        _ => panic!("unexpected method {method}"),
+    }
+}
+
+fn config() -> Config {
+    let mut config = Config {
+        network_passphrase: "Standalone Network ; February 2017".to_string(),
+        secret_key: [9; 32],
+        allowed_calls: Vec::new(),
+        profile_contract: Some(PROFILE_CONTRACT),
+        premium_contract: Some(PREMIUM_CONTRACT),
+        premium_feature: "unlimited_messages".to_string(),
+        free_limit: 2,
+        premium_limit: 5,
+        base_fee: 100,
+        max_resource_fee: 10_000,
+    };
+    config.allow_contract(POST_CONTRACT, &POST_FUNCTIONS);
+    config.allow_contract(MESSAGING_CONTRACT, &MESSAGING_FUNCTIONS);
+    config
+}
+
+fn address(user: &SigningKey) -> ScAddress {
+    ScAddress::Account(account_id(user.verifying_key().to_bytes()))
+}
+
+// Keys 4 and 5 have profiles; other keys do not
+fn setup() -> (Arc<Relayer<HttpRpc>>, Arc<Mutex<Ledger>>) {
+    let profiles = [4, 5].map(|seed| address(&SigningKey::from_bytes(&[seed; 32]))).to_vec();
+    let ledger = Arc::new(Mutex::new(Ledger { profiles, ..Ledger::default() }));
+    let relayer = Relayer::new(config(), HttpRpc::new(&stand_in_rpc(ledger.clone())));
+    (Arc::new(relayer), ledger)
+}
+
+fn invoke(contract: [u8; 32], function: &str, args: Vec<ScVal>) -> InvokeContractArgs {
+    InvokeContractArgs {
+        contract_address: ScAddress::Contract(Hash(contract)),
+        function_name: symbol(function).unwrap(),
+        args: args.try_into().unwrap(),
+    }
+}
+
+fn anonymous_post() -> HostFunction {
+    HostFunction::InvokeContract(invoke(POST_CONTRACT, "create_anonymous_post", vec![ScVal::Void]))
+}
+
+fn address_auth(user: &SigningKey, call: InvokeContractArgs) -> SorobanAuthorizationEntry {
+    SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+            address: address(user),
+            nonce: 1,
+            signature_expiration_ledger: 100,
+            signature: ScVal::Void,
+        }),
+        root_invocation: SorobanAuthorizedInvocation {
+            function: SorobanAuthorizedFunction::ContractFn(call),
+            sub_invocations: Default::default(),
+        },
+    }
+}
+
+fn signed(
+    requester: &SigningKey,
+    host_function: HostFunction,
+    auth: Vec<SorobanAuthorizationEntry>,
+    nonce: u64,
+    expires_at: u64,
+) -> RelayRequest {
+    let passphrase = "Standalone Network ; February 2017";
+    let digest = request_digest(&network_id(passphrase), &host_function, nonce, expires_at).unwrap();
+    RelayRequest {
+        host_function,
+        auth,
+        requester: requester.verifying_key().to_bytes(),
+        nonce,
+        expires_at,
+        signature: requester.sign(&digest).to_bytes(),
+    }
+}
+
+// A request with a fresh nonce, valid for a minute from time 0
+fn request(requester: &SigningKey, host_function: HostFunction, auth: Vec<SorobanAuthorizationEntry>) -> RelayRequest {
+    static NONCE: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    signed(requester, host_function, auth, NONCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed), 60)
+}
+
+#[test]
+fn test_relays_through_http_api() {
+    let (relayer, ledger) = setup();
+    let user = SigningKey::from_bytes(&[4; 32]);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/relay", listener.local_addr().unwrap());
+    let relayer_key = relayer.signing_key.verifying_key();
+    thread::spawn(move || serve(listener, relayer));
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let signed = signed(&user, anonymous_post(), Vec::new(), 0, now + 60);
+    let body = json!({
+        "host_function": to_base64(&signed.host_function).unwrap(),
+        "auth": [],
+        "requester": stellar_strkey::ed25519::PublicKey(signed.requester).to_string(),
+        "nonce": signed.nonce,
+        "expires_at": signed.expires_at,
+        "signature": signed.signature.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+    });
+    let response: Value = serde_json::from_str(&http_post(&url, &body.to_string()).unwrap()).unwrap();
+    assert_eq!(response["hash"], format!("{:064x}", 1));
+
+    // Submitted from the relayer's account, signed by it and without any trace of the requester
+    let ledger = ledger.lock().unwrap();
+    let TransactionEnvelope::Tx(envelope) = &ledger.sent[0] else { panic!("unexpected envelope") };
+    assert_eq!(envelope.tx.source_account, MuxedAccount::Ed25519(Uint256(relayer_key.to_bytes())));
+    assert_eq!(envelope.tx.seq_num, SequenceNumber(RELAYER_SEQUENCE + 1));
+    assert_eq!(envelope.tx.fee, 100 + RESOURCE_FEE as u32);
+    let payload = TransactionSignaturePayload {
+        network_id: network_id("Standalone Network ; February 2017"),
+        tagged_transaction: TransactionSignaturePayloadTaggedTransaction::Tx(envelope.tx.clone()),
+    };
+    let hash = Sha256::digest(payload.to_xdr(Limits::none()).unwrap());
+    let signature = ed25519_dalek::Signature::from_slice(envelope.signatures[0].signature.as_slice()).unwrap();
+    assert!(relayer_key.verify(&hash, &signature).is_ok());
+    let xdr = ledger.sent[0].to_xdr(Limits::none()).unwrap();
+    assert!(!xdr.windows(32).any(|window| window == signed.requester));
+}
+
+#[test]
+fn test_rejects_calls_outside_allow_list() {
+    let (relayer, ledger) = setup();
+    let user = SigningKey::from_bytes(&[4; 32]);
+
+    let admin_call = HostFunction::InvokeContract(invoke(POST_CONTRACT, "set_paused", vec![ScVal::Bool(true)]));
+    let other_contract = HostFunction::InvokeContract(invoke([8; 32], "create_anonymous_post", vec![ScVal::Void]));
+    for host_function in [admin_call, other_contract] {
+        let result = relayer.relay(&request(&user, host_function, Vec::new()), 0);
+        assert!(matches!(result, Err(RelayError::NotAllowed(_))));
+    }
+    assert!(ledger.lock().unwrap().sent.is_empty());
+}
+
+#[test]
+fn test_checks_auth_entries() {
+    let (relayer, ledger) = setup();
+    let user = SigningKey::from_bytes(&[4; 32]);
+    let send = invoke(MESSAGING_CONTRACT, "send_message", vec![ScVal::U64(0)]);
+    let host_function = HostFunction::InvokeContract(send.clone());
+
+    // Source account credentials would be authorized by the relayer's own signature
+    let mut source_auth = address_auth(&user, send.clone());
+    source_auth.credentials = SorobanCredentials::SourceAccount;
+    let result = relayer.relay(&request(&user, host_function.clone(), vec![source_auth]), 0);
+    assert!(matches!(result, Err(RelayError::NotAllowed(_))));
+
+    let other_call = address_auth(&user, invoke(MESSAGING_CONTRACT, "send_message", vec![ScVal::U64(1)]));
+    let result = relayer.relay(&request(&user, host_function.clone(), vec![other_call]), 0);
+    assert!(matches!(result, Err(RelayError::NotAllowed(_))));
+
+    // Relay limits follow the authorizing address, so another requester cannot relay the call
+    let other = SigningKey::from_bytes(&[5; 32]);
+    let result = relayer.relay(&request(&other, host_function.clone(), vec![address_auth(&user, send.clone())]), 0);
+    assert!(matches!(result, Err(RelayError::NotAllowed(_))));
+    let mixed = vec![address_auth(&user, send.clone()), address_auth(&other, send.clone())];
+    let result = relayer.relay(&request(&user, host_function.clone(), mixed), 0);
+    assert!(matches!(result, Err(RelayError::NotAllowed(_))));
+    assert!(ledger.lock().unwrap().sent.is_empty());
+
+    relayer.relay(&request(&user, host_function, vec![address_auth(&user, send)]), 0).unwrap();
+    let TransactionEnvelope::Tx(envelope) = &ledger.lock().unwrap().sent[0] else { panic!("unexpected envelope") };
+    let OperationBody::InvokeHostFunction(op) = &envelope.tx.operations[0].body else { panic!("unexpected operation") };
+    assert_eq!(op.auth.len(), 1);
+}
+
+#[test]
+fn test_rejects_bad_requests() {
+    let (relayer, _) = setup();
+    let user = SigningKey::from_bytes(&[4; 32]);
+    let forger = SigningKey::from_bytes(&[5; 32]);
+
+    let mut forged = request(&forger, anonymous_post(), Vec::new());
+    forged.requester = user.verifying_key().to_bytes();
+    assert!(matches!(relayer.relay(&forged, 0), Err(RelayError::NotAllowed(_))));
+
+    assert!(matches!(RelayRequest::from_json("not json"), Err(RelayError::BadRequest(_))));
+    let missing_signature = json!({"host_function": to_base64(&anonymous_post()).unwrap(), "requester": "G"});
+    assert!(matches!(RelayRequest::from_json(&missing_signature.to_string()), Err(RelayError::BadRequest(_))));
+}
+
+#[test]
+fn test_premium_raises_relay_limit() {
+    let (relayer, ledger) = setup();
+    let free = SigningKey::from_bytes(&[4; 32]);
+    let premium = SigningKey::from_bytes(&[5; 32]);
+    ledger.lock().unwrap().premium.push(premium.verifying_key().to_bytes());
+
+    relayer.relay(&request(&free, anonymous_post(), Vec::new()), 0).unwrap();
+    relayer.relay(&request(&free, anonymous_post(), Vec::new()), 0).unwrap();
+    assert_eq!(relayer.relay(&request(&free, anonymous_post(), Vec::new()), 0), Err(RelayError::RateLimited));
+    assert_eq!(relayer.remaining_quota(&address(&free), 0), Ok(0));
+
+    // The window rolls forward
+    assert_eq!(relayer.remaining_quota(&address(&free), RATE_WINDOW), Ok(2));
+    relayer.relay(&signed(&free, anonymous_post(), Vec::new(), 0, RATE_WINDOW + 60), RATE_WINDOW).unwrap();
+
+    for _ in 0..3 {
+        relayer.relay(&request(&premium, anonymous_post(), Vec::new()), 0).unwrap();
+    }
+    assert_eq!(relayer.remaining_quota(&address(&premium), 0), Ok(2));
+}
+
+#[test]
+fn test_back_to_back_submits_use_fresh_sequences() {
+    let (relayer, ledger) = setup();
+    let user = SigningKey::from_bytes(&[5; 32]);
+
+    // The stand-in ledger never applies anything, so its account sequence stays put
+    relayer.relay(&request(&user, anonymous_post(), Vec::new()), 0).unwrap();
+    relayer.relay(&request(&user, anonymous_post(), Vec::new()), 0).unwrap();
+    let sequences: Vec<_> = ledger
+        .lock()
+        .unwrap()
+        .sent
+        .iter()
+        .map(|envelope| match envelope {
+            TransactionEnvelope::Tx(envelope) => envelope.tx.seq_num.clone(),
+            _ => panic!("unexpected envelope"),
+        })
+        .collect();
+    assert_eq!(sequences, [SequenceNumber(RELAYER_SEQUENCE + 1), SequenceNumber(RELAYER_SEQUENCE + 2)]);
+}
+
+#[test]
+fn test_concurrent_requests_share_relay_limit() {
+    let (relayer, ledger) = setup();
+    let user = SigningKey::from_bytes(&[4; 32]);
+
+    let workers: Vec<_> = (0..8)
+        .map(|_| {
+            let relayer = relayer.clone();
+            let request = request(&user, anonymous_post(), Vec::new());
+            thread::spawn(move || relayer.relay(&request, 0).is_ok())
+        })
+        .collect();
+    let relayed = workers.into_iter().map(|worker| worker.join().unwrap()).filter(|ok| *ok).count();
+    assert_eq!(relayed, 2);
+    assert_eq!(ledger.lock().unwrap().sent.len(), 2);
+}
+
+#[test]
+fn test_requires_on_chain_profile() {
+    let (relayer, ledger) = setup();
+    let stranger = SigningKey::from_bytes(&[7; 32]);
+
+    let result = relayer.relay(&request(&stranger, anonymous_post(), Vec::new()), 0);
+    assert!(matches!(result, Err(RelayError::NotAllowed(_))));
+    assert!(ledger.lock().unwrap().sent.is_empty());
+
+    ledger.lock().unwrap().profiles.push(address(&stranger));
+    relayer.relay(&request(&stranger, anonymous_post(), Vec::new()), 0).unwrap();
+}
+
+#[test]
+fn test_rejects_replayed_and_expired_requests() {
+    let (relayer, ledger) = setup();
+    let user = SigningKey::from_bytes(&[4; 32]);
+
+    let first = signed(&user, anonymous_post(), Vec::new(), 1, 60);
+    relayer.relay(&first, 0).unwrap();
+    assert!(matches!(relayer.relay(&first, 10), Err(RelayError::NotAllowed(_))));
+
+    // The same call under a new nonce is a new request
+    relayer.relay(&signed(&user, anonymous_post(), Vec::new(), 2, 60), 10).unwrap();
+
+    // Expired, or valid for longer than the relayer remembers requests
+    let expired = signed(&user, anonymous_post(), Vec::new(), 3, 60);
+    assert!(matches!(relayer.relay(&expired, 60), Err(RelayError::NotAllowed(_))));
+    let too_long = signed(&user, anonymous_post(), Vec::new(), 4, MAX_REQUEST_TTL + 1);
+    assert!(matches!(relayer.relay(&too_long, 0), Err(RelayError::NotAllowed(_))));
+
+    // A tampered nonce no longer matches the signature
+    let mut tampered = signed(&user, anonymous_post(), Vec::new(), 5, 60);
+    tampered.nonce = 6;
+    assert!(matches!(relayer.relay(&tampered, 0), Err(RelayError::NotAllowed(_))));
+    assert_eq!(ledger.lock().unwrap().sent.len(), 2);
+}
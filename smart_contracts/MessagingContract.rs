@@ -1,7 +1,42 @@
 #![no_std]
-use soroban_sdk::{contractimpl, Address, Env, String, Vec, u64};
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, Address,
+    BytesN, Env, String, Symbol, Vec,
+};
 
+// Length of the rolling window free-tier quotas are counted over
+const QUOTA_WINDOW: u64 = 24 * 60 * 60;
+
+// Bumped whenever stored data needs migrating after an upgrade
+const SCHEMA_VERSION: u32 = 1;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum MessagingError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    QuotaExceeded = 3,
+    Paused = 4,
+    UnknownSchemaVersion = 5,
+}
+
+#[contracttype]
 #[derive(Clone)]
+pub enum DataKey {
+    Admin,
+    PremiumContract,
+    DailyQuota,
+    MessageCount,
+    Message(u64),
+    SentLog(Address),
+    Paused,
+    SchemaVersion,
+    AnalyticsContract,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Message {
     pub id: u64,
     pub from: Address,
@@ -11,26 +46,183 @@ pub struct Message {
     pub revealed: bool,
 }
 
+// The subset of PremiumContract used to check for unlimited messaging
+#[contractclient(name = "PremiumClient")]
+pub trait PremiumInterface {
+    fn has_feature_access(env: Env, user: Address, feature_name: Symbol) -> bool;
+}
+
+// The UserProfileContract::AnalyticsEvent variants this contract reports
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AnalyticsEvent {
+    MessageReceived,
+}
+
+// The subset of UserProfileContract used to report profile activity
+#[contractclient(name = "AnalyticsClient")]
+pub trait AnalyticsInterface {
+    fn record_event(env: Env, reporter: Address, user: Address, event: AnalyticsEvent);
+}
+
+#[contract]
 pub struct MessagingContract;
 
 #[contractimpl]
 impl MessagingContract {
+    pub fn initialize(env: Env, admin: Address, premium_contract: Address, daily_quota: u32) {
+        if env.storage().instance().has(&DataKey::Admin) {
+            panic_with_error!(&env, MessagingError::AlreadyInitialized);
+        }
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::PremiumContract, &premium_contract);
+        env.storage().instance().set(&DataKey::DailyQuota, &daily_quota);
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+    }
+
+    // Pause or resume sending and revealing. Reads keep working.
+    pub fn set_paused(env: Env, paused: bool) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    // Swap in new code, keeping messages and send logs; run `migrate` if the schema version moved
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
+        Self::require_admin(&env);
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+    }
+
+    // Bring stored data up to the current schema version
+    pub fn migrate(env: Env) -> u32 {
+        Self::require_admin(&env);
+        let version = Self::get_schema_version(env.clone());
+        if version > SCHEMA_VERSION {
+            panic_with_error!(&env, MessagingError::UnknownSchemaVersion);
+        }
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+        SCHEMA_VERSION
+    }
+
+    pub fn get_schema_version(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::SchemaVersion).unwrap_or(0)
+    }
+
+    // Report received messages to UserProfileContract. This contract must be registered there as a reporter.
+    pub fn set_analytics_contract(env: Env, analytics_contract: Address) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::AnalyticsContract, &analytics_contract);
+    }
+
+    pub fn set_daily_quota(env: Env, daily_quota: u32) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::DailyQuota, &daily_quota);
+    }
+
+    pub fn get_daily_quota(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::DailyQuota).unwrap_or(0)
+    }
+
     pub fn send_message(env: Env, from: Address, to: Address, content: String, timestamp: u64) {
-        let id = env.storage().get::<_, u64>(&"msg_id").unwrap_or(0) + 1;
-        let msg = Message { id, from, to, content, timestamp, revealed: false };
-        env.storage().set(&id, &msg);
-        env.storage().set(&"msg_id", &id);
+        Self::require_not_paused(&env);
+        from.require_auth();
+        if !Self::has_unlimited_messages(&env, &from) {
+            Self::consume_quota(&env, &from);
+        }
+
+        let id = env.storage().instance().get::<_, u64>(&DataKey::MessageCount).unwrap_or(0) + 1;
+        let msg = Message { id, from, to: to.clone(), content, timestamp, revealed: false };
+        env.storage().persistent().set(&DataKey::Message(id), &msg);
+        env.storage().instance().set(&DataKey::MessageCount, &id);
+
+        // Analytics are best effort and never block a message
+        if let Some(analytics) = env.storage().instance().get::<_, Address>(&DataKey::AnalyticsContract) {
+            let _ = AnalyticsClient::new(&env, &analytics).try_record_event(
+                &env.current_contract_address(),
+                &to,
+                &AnalyticsEvent::MessageReceived,
+            );
+        }
     }
 
+    // Reveal the sender of a message. Only the sender may do this.
     pub fn reveal_identity(env: Env, id: u64) {
-        if let Some(mut msg) = env.storage().get::<_, Message>(&id) {
+        Self::require_not_paused(&env);
+        if let Some(mut msg) = env.storage().persistent().get::<_, Message>(&DataKey::Message(id)) {
+            msg.from.require_auth();
             msg.revealed = true;
-            env.storage().set(&id, &msg);
+            env.storage().persistent().set(&DataKey::Message(id), &msg);
         }
     }
 
-    pub fn get_messages(env: Env, user1: Address, user2: Address) -> Vec<Message> {
+    pub fn get_messages(env: Env, _user1: Address, _user2: Address) -> Vec<Message> {
         // Placeholder: Real implementation would filter messages between user1 and user2
         Vec::new(&env)
     }
+
+    // Messages the user can still send in the current rolling window.
+    // Users with the unlimited_messages feature get u32::MAX.
+    pub fn get_remaining_quota(env: Env, user: Address) -> u32 {
+        if Self::has_unlimited_messages(&env, &user) {
+            return u32::MAX;
+        }
+        let sent = Self::recent_sends(&env, &user).len();
+        Self::get_daily_quota(env).saturating_sub(sent)
+    }
+
+    fn require_admin(env: &Env) {
+        let admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .unwrap_or_else(|| panic_with_error!(env, MessagingError::NotInitialized));
+        admin.require_auth();
+    }
+
+    fn require_not_paused(env: &Env) {
+        if Self::is_paused(env.clone()) {
+            panic_with_error!(env, MessagingError::Paused);
+        }
+    }
+
+    fn has_unlimited_messages(env: &Env, user: &Address) -> bool {
+        let premium: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PremiumContract)
+            .unwrap_or_else(|| panic_with_error!(env, MessagingError::NotInitialized));
+        PremiumClient::new(env, &premium).has_feature_access(user, &Symbol::new(env, "unlimited_messages"))
+    }
+
+    // Send timestamps of the user that still fall inside the rolling window
+    fn recent_sends(env: &Env, user: &Address) -> Vec<u64> {
+        let now = env.ledger().timestamp();
+        let log: Vec<u64> = env.storage().persistent().get(&DataKey::SentLog(user.clone())).unwrap_or(Vec::new(env));
+        let mut recent = Vec::new(env);
+        for sent_at in log.iter() {
+            if sent_at + QUOTA_WINDOW > now {
+                recent.push_back(sent_at);
+            }
+        }
+        recent
+    }
+
+    // Log a send against the user's free-tier quota, failing if it is used up
+    fn consume_quota(env: &Env, user: &Address) {
+        let mut recent = Self::recent_sends(env, user);
+        if recent.len() >= Self::get_daily_quota(env.clone()) {
+            panic_with_error!(env, MessagingError::QuotaExceeded);
+        }
+        recent.push_back(env.ledger().timestamp());
+        env.storage().persistent().set(&DataKey::SentLog(user.clone()), &recent);
+    }
 }
+
+#[cfg(test)]
+#[path = "MessagingContract_test.rs"]
+mod test;
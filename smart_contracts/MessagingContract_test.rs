@@ -1 +1,160 @@
- 
\ No newline at end of file
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::{contract, contractimpl};
+
+// Stand-in for PremiumContract that grants every feature to a single address
+#[contract]
+pub struct MockPremium;
+
+#[contractimpl]
+impl MockPremium {
+    pub fn set_premium_user(env: Env, user: Address) {
+        env.storage().instance().set(&0u32, &user);
+    }
+
+    pub fn has_feature_access(env: Env, user: Address, _feature_name: Symbol) -> bool {
+        env.storage().instance().get::<_, Address>(&0u32) == Some(user)
+    }
+}
+
+// Stand-in for UserProfileContract that counts received messages, or fails once `block` is called
+#[contract]
+pub struct MockAnalytics;
+
+#[contractimpl]
+impl MockAnalytics {
+    pub fn block(env: Env) {
+        env.storage().instance().set(&symbol_short!("blocked"), &true);
+    }
+
+    pub fn record_event(env: Env, reporter: Address, user: Address, event: AnalyticsEvent) {
+        reporter.require_auth();
+        assert!(!env.storage().instance().has(&symbol_short!("blocked")));
+        assert_eq!(event, AnalyticsEvent::MessageReceived);
+        let count = env.storage().instance().get::<_, u32>(&user).unwrap_or(0);
+        env.storage().instance().set(&user, &(count + 1));
+    }
+
+    pub fn messages_received(env: Env, user: Address) -> u32 {
+        env.storage().instance().get(&user).unwrap_or(0)
+    }
+}
+
+fn setup(env: &Env, daily_quota: u32) -> (MessagingContractClient<'_>, MockPremiumClient<'_>) {
+    env.mock_all_auths();
+    let premium = MockPremiumClient::new(env, &env.register_contract(None, MockPremium));
+    let client = MessagingContractClient::new(env, &env.register_contract(None, MessagingContract));
+    client.initialize(&Address::generate(env), &premium.address, &daily_quota);
+    (client, premium)
+}
+
+#[test]
+fn test_free_tier_quota() {
+    let env = Env::default();
+    let (client, _) = setup(&env, 2);
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    let content = String::from_str(&env, "hi");
+
+    assert_eq!(client.get_remaining_quota(&from), 2);
+    client.send_message(&from, &to, &content, &0);
+    client.send_message(&from, &to, &content, &0);
+    assert_eq!(client.get_remaining_quota(&from), 0);
+    assert!(client.try_send_message(&from, &to, &content, &0).is_err());
+
+    // The window rolls forward
+    env.ledger().with_mut(|li| li.timestamp += QUOTA_WINDOW);
+    assert_eq!(client.get_remaining_quota(&from), 2);
+    client.send_message(&from, &to, &content, &0);
+}
+
+#[test]
+fn test_unlimited_messages_bypasses_quota() {
+    let env = Env::default();
+    let (client, premium) = setup(&env, 1);
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    let content = String::from_str(&env, "hi");
+    premium.set_premium_user(&from);
+
+    client.send_message(&from, &to, &content, &0);
+    client.send_message(&from, &to, &content, &0);
+    assert_eq!(client.get_remaining_quota(&from), u32::MAX);
+}
+
+#[test]
+fn test_admin_sets_quota() {
+    let env = Env::default();
+    let (client, _) = setup(&env, 1);
+    let from = Address::generate(&env);
+
+    client.set_daily_quota(&5);
+    assert_eq!(client.get_daily_quota(), 5);
+    assert_eq!(client.get_remaining_quota(&from), 5);
+}
+
+#[test]
+fn test_pause_blocks_sending() {
+    let env = Env::default();
+    let (client, _) = setup(&env, 5);
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    let content = String::from_str(&env, "hi");
+
+    client.set_paused(&true);
+    assert!(client.is_paused());
+    assert!(client.try_send_message(&from, &to, &content, &0).is_err());
+    assert_eq!(client.get_remaining_quota(&from), 5);
+
+    client.set_paused(&false);
+    client.send_message(&from, &to, &content, &0);
+    assert_eq!(client.get_schema_version(), 1);
+}
+
+#[test]
+fn test_reports_received_messages() {
+    let env = Env::default();
+    let (client, _) = setup(&env, 5);
+    let analytics = MockAnalyticsClient::new(&env, &env.register_contract(None, MockAnalytics));
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    client.set_analytics_contract(&analytics.address);
+
+    client.send_message(&from, &to, &String::from_str(&env, "hi"), &0);
+    assert_eq!(analytics.messages_received(&to), 1);
+    assert_eq!(analytics.messages_received(&from), 0);
+}
+
+#[test]
+fn test_failed_report_does_not_block_message() {
+    let env = Env::default();
+    let (client, _) = setup(&env, 2);
+    let analytics = MockAnalyticsClient::new(&env, &env.register_contract(None, MockAnalytics));
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    client.set_analytics_contract(&analytics.address);
+    analytics.block();
+
+    client.send_message(&from, &to, &String::from_str(&env, "hi"), &0);
+    assert_eq!(client.get_remaining_quota(&from), 1);
+    let stored: Option<Message> =
+        env.as_contract(&client.address, || env.storage().persistent().get(&DataKey::Message(1)));
+    assert_eq!(stored.unwrap().to, to);
+}
+
+#[test]
+fn test_only_sender_reveals_identity() {
+    let env = Env::default();
+    let (client, _) = setup(&env, 5);
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    client.send_message(&from, &to, &String::from_str(&env, "hi"), &0);
+
+    client.reveal_identity(&1);
+    assert_eq!(env.auths()[0].0, from);
+    let stored: Option<Message> =
+        env.as_contract(&client.address, || env.storage().persistent().get(&DataKey::Message(1)));
+    assert!(stored.unwrap().revealed);
+}
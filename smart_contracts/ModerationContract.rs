@@ -0,0 +1,569 @@
+#![no_std]
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, token, vec,
+    xdr::ToXdr, Address, Bytes, BytesN, Env, Vec,
+};
+
+// Bumped whenever stored data needs migrating after an upgrade
+const SCHEMA_VERSION: u32 = 1;
+
+const TOTAL_BPS: i128 = 10_000;
+
+// Staked members are kept in pages of JUROR_PAGE addresses. Unstaking moves the last member into
+// the freed slot, so the pool stays dense and a slot can be picked at random.
+const JUROR_PAGE: u32 = 50;
+// Upper bound for a single get_jurors call
+const MAX_JUROR_PAGE: u32 = 50;
+// A jury is drawn from at most this many consecutive members, starting at a random slot, so
+// opening a case reads a bounded number of juror records however large the pool grows
+const MAX_DRAW_POOL: u32 = 100;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ModerationError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    Paused = 3,
+    UnknownSchemaVersion = 4,
+    InvalidAmount = 5,
+    StakeLocked = 6,
+    NotReported = 7,
+    CaseAlreadyOpen = 8,
+    CaseNotFound = 9,
+    NotJuror = 10,
+    AlreadyVoted = 11,
+    VotingClosed = 12,
+    VotingOpen = 13,
+    NotEnoughJurors = 14,
+    InvalidConfig = 15,
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub enum DataKey {
+    Admin,
+    Paused,
+    SchemaVersion,
+    PostContract,
+    StakeToken,
+    JuryConfig,
+    JurorCount,
+    JurorPage(u32),     // Vec<Address> of staked members
+    JurorSlot(Address), // a staked member's slot in the pool
+    Juror(Address),
+    CaseCount,
+    Case(u64),
+    OpenCase(Target),
+    Vote(u64, Address),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JuryConfig {
+    pub min_stake: i128, // stake needed to be drawn for a jury
+    pub jury_size: u32,
+    pub voting_period: u64, // seconds from opening a case to its deadline
+    pub slash_bps: u32,     // share of stake lost by minority voters and jurors who did not vote
+    pub case_fee: i128,     // paid by whoever opens a case and shared among the majority
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Juror {
+    pub stake: i128,
+    pub active_cases: u32, // stake cannot be added or withdrawn while this is non-zero
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ballot {
+    pub hide: bool,
+    pub weight: i128, // the juror's stake when the vote was cast
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CaseStatus {
+    Open,
+    Hidden,
+    Kept,
+    Expired, // nobody voted; PostContract visibility is left alone
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Case {
+    pub id: u64,
+    pub target: Target,
+    pub opened_by: Address,
+    pub jury: Vec<Address>,
+    pub deadline: u64,
+    pub hide_weight: i128,
+    pub keep_weight: i128,
+    pub votes: u32,
+    pub status: CaseStatus,
+    pub fee: i128,
+}
+
+// Mirrors PostContract::Target
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Target {
+    Post(u64),
+    Comment(u64),
+}
+
+// Mirrors PostContract::ModerationStatus
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ModerationStatus {
+    Visible,
+    Hidden,
+    Appealed,
+    Restored,
+    Upheld,
+}
+
+// Mirrors PostContract::ModerationState
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModerationState {
+    pub reports: u32,
+    pub weight: u32,
+    pub status: ModerationStatus,
+}
+
+// The subset of PostContract used to check reports and apply verdicts
+#[contractclient(name = "PostClient")]
+pub trait PostInterface {
+    fn get_moderation(env: Env, target: Target) -> ModerationState;
+    fn apply_moderation(env: Env, target: Target, hidden: bool) -> ModerationState;
+}
+
+#[contract]
+pub struct ModerationContract;
+
+#[contractimpl]
+impl ModerationContract {
+    pub fn initialize(env: Env, admin: Address, post_contract: Address, stake_token: Address, config: JuryConfig) {
+        if env.storage().instance().has(&DataKey::Admin) {
+            panic_with_error!(&env, ModerationError::AlreadyInitialized);
+        }
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::PostContract, &post_contract);
+        env.storage().instance().set(&DataKey::StakeToken, &stake_token);
+        Self::require_valid_config(&env, &config);
+        env.storage().instance().set(&DataKey::JuryConfig, &config);
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+    }
+
+    // Pause or resume staking, cases and voting. Reads keep working.
+    pub fn set_paused(env: Env, paused: bool) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    // Swap in new code without touching open reports or cases; `migrate` handles schema changes
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
+        Self::require_admin(&env);
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+    }
+
+    // Bring stored data up to the current schema version
+    pub fn migrate(env: Env) -> u32 {
+        Self::require_admin(&env);
+        let version = Self::get_schema_version(env.clone());
+        if version > SCHEMA_VERSION {
+            panic_with_error!(&env, ModerationError::UnknownSchemaVersion);
+        }
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+        SCHEMA_VERSION
+    }
+
+    pub fn get_schema_version(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::SchemaVersion).unwrap_or(0)
+    }
+
+    // Applies to cases opened afterwards
+    pub fn set_jury_config(env: Env, config: JuryConfig) {
+        Self::require_admin(&env);
+        Self::require_valid_config(&env, &config);
+        env.storage().instance().set(&DataKey::JuryConfig, &config);
+    }
+
+    pub fn get_jury_config(env: Env) -> JuryConfig {
+        env.storage()
+            .instance()
+            .get(&DataKey::JuryConfig)
+            .unwrap_or_else(|| panic_with_error!(&env, ModerationError::NotInitialized))
+    }
+
+    // Lock stake tokens. Members with at least min_stake can be drawn for juries.
+    // Not possible while serving on an open case, so votes cannot be outweighed after the fact.
+    pub fn stake(env: Env, juror: Address, amount: i128) -> Juror {
+        Self::require_not_paused(&env);
+        juror.require_auth();
+        if amount <= 0 {
+            panic_with_error!(&env, ModerationError::InvalidAmount);
+        }
+        let mut record = Self::get_juror(env.clone(), juror.clone());
+        if record.active_cases > 0 {
+            panic_with_error!(&env, ModerationError::StakeLocked);
+        }
+        token::Client::new(&env, &Self::stake_token(&env)).transfer(&juror, &env.current_contract_address(), &amount);
+
+        record.stake += amount;
+        env.storage().persistent().set(&DataKey::Juror(juror.clone()), &record);
+        if !env.storage().persistent().has(&DataKey::JurorSlot(juror.clone())) {
+            Self::add_to_pool(&env, &juror);
+        }
+        record
+    }
+
+    // Withdraw stake, including rewards. Not possible while serving on an open case.
+    pub fn unstake(env: Env, juror: Address, amount: i128) -> Juror {
+        Self::require_not_paused(&env);
+        juror.require_auth();
+        let mut record = Self::get_juror(env.clone(), juror.clone());
+        if amount <= 0 || amount > record.stake {
+            panic_with_error!(&env, ModerationError::InvalidAmount);
+        }
+        if record.active_cases > 0 {
+            panic_with_error!(&env, ModerationError::StakeLocked);
+        }
+
+        record.stake -= amount;
+        env.storage().persistent().set(&DataKey::Juror(juror.clone()), &record);
+        if record.stake == 0 {
+            Self::remove_from_pool(&env, &juror);
+        }
+        token::Client::new(&env, &Self::stake_token(&env)).transfer(&env.current_contract_address(), &juror, &amount);
+        record
+    }
+
+    pub fn get_juror(env: Env, juror: Address) -> Juror {
+        env.storage().persistent().get(&DataKey::Juror(juror)).unwrap_or_default()
+    }
+
+    // Get a page of staked members. Unstaking reorders the pool.
+    pub fn get_jurors(env: Env, offset: u32, limit: u32) -> Vec<Address> {
+        Self::juror_range(&env, offset, offset.saturating_add(limit.min(MAX_JUROR_PAGE)))
+    }
+
+    pub fn get_juror_count(env: Env) -> u32 {
+        env.storage().persistent().get(&DataKey::JurorCount).unwrap_or(0)
+    }
+
+    // Send reported content to a jury for case_fee. The opener is never drawn for their own case.
+    pub fn open_case(env: Env, opened_by: Address, target: Target) -> Case {
+        Self::require_not_paused(&env);
+        opened_by.require_auth();
+        if env.storage().persistent().has(&DataKey::OpenCase(target.clone())) {
+            panic_with_error!(&env, ModerationError::CaseAlreadyOpen);
+        }
+        if Self::post_client(&env).get_moderation(&target).reports == 0 {
+            panic_with_error!(&env, ModerationError::NotReported);
+        }
+
+        let config = Self::get_jury_config(env.clone());
+        let id = env.storage().instance().get::<_, u64>(&DataKey::CaseCount).unwrap_or(0) + 1;
+        let jury = Self::draw_jury(&env, id, &target, &opened_by, &config);
+        if config.case_fee > 0 {
+            token::Client::new(&env, &Self::stake_token(&env)).transfer(
+                &opened_by,
+                &env.current_contract_address(),
+                &config.case_fee,
+            );
+        }
+        for juror in jury.iter() {
+            let mut record = Self::get_juror(env.clone(), juror.clone());
+            record.active_cases += 1;
+            env.storage().persistent().set(&DataKey::Juror(juror), &record);
+        }
+
+        let case = Case {
+            id,
+            target: target.clone(),
+            opened_by,
+            jury,
+            deadline: env.ledger().timestamp() + config.voting_period,
+            hide_weight: 0,
+            keep_weight: 0,
+            votes: 0,
+            status: CaseStatus::Open,
+            fee: config.case_fee,
+        };
+        env.storage().persistent().set(&DataKey::Case(id), &case);
+        env.storage().persistent().set(&DataKey::OpenCase(target), &id);
+        env.storage().instance().set(&DataKey::CaseCount, &id);
+        env.events().publish((symbol_short!("case"), id), case.jury.clone());
+        case
+    }
+
+    pub fn get_case(env: Env, case_id: u64) -> Option<Case> {
+        env.storage().persistent().get(&DataKey::Case(case_id))
+    }
+
+    // Vote to hide or keep the content. Votes weigh the juror's stake at the time of voting.
+    pub fn vote(env: Env, juror: Address, case_id: u64, hide: bool) -> Case {
+        Self::require_not_paused(&env);
+        juror.require_auth();
+        let mut case = Self::load_case(&env, case_id);
+        if case.status != CaseStatus::Open || env.ledger().timestamp() >= case.deadline {
+            panic_with_error!(&env, ModerationError::VotingClosed);
+        }
+        if !case.jury.contains(&juror) {
+            panic_with_error!(&env, ModerationError::NotJuror);
+        }
+        let key = DataKey::Vote(case_id, juror.clone());
+        if env.storage().persistent().has(&key) {
+            panic_with_error!(&env, ModerationError::AlreadyVoted);
+        }
+
+        let weight = Self::get_juror(env.clone(), juror).stake;
+        env.storage().persistent().set(&key, &Ballot { hide, weight });
+        if hide {
+            case.hide_weight += weight;
+        } else {
+            case.keep_weight += weight;
+        }
+        case.votes += 1;
+        env.storage().persistent().set(&DataKey::Case(case_id), &case);
+        case
+    }
+
+    // Close a case once every juror has voted or the deadline has passed. Anyone can call this.
+    // Ties keep the content. Minority voters and jurors who did not vote lose slash_bps of their
+    // stake, shared among the majority by vote weight along with the case fee. The fee is refunded
+    // if nobody voted.
+    pub fn finalize(env: Env, case_id: u64) -> Case {
+        Self::require_not_paused(&env);
+        let mut case = Self::load_case(&env, case_id);
+        if case.status != CaseStatus::Open {
+            panic_with_error!(&env, ModerationError::VotingClosed);
+        }
+        if case.votes < case.jury.len() && env.ledger().timestamp() < case.deadline {
+            panic_with_error!(&env, ModerationError::VotingOpen);
+        }
+
+        if case.votes == 0 {
+            case.status = CaseStatus::Expired;
+            if case.fee > 0 {
+                token::Client::new(&env, &Self::stake_token(&env)).transfer(
+                    &env.current_contract_address(),
+                    &case.opened_by,
+                    &case.fee,
+                );
+            }
+        } else {
+            let hide = case.hide_weight > case.keep_weight;
+            case.status = if hide { CaseStatus::Hidden } else { CaseStatus::Kept };
+            Self::settle_stakes(&env, &case, hide);
+            // The verdict still settles if the content was removed while the case was open
+            let _ = Self::post_client(&env).try_apply_moderation(&case.target, &hide);
+        }
+        for juror in case.jury.iter() {
+            let mut record = Self::get_juror(env.clone(), juror.clone());
+            record.active_cases -= 1;
+            env.storage().persistent().set(&DataKey::Juror(juror), &record);
+        }
+
+        env.storage().persistent().set(&DataKey::Case(case_id), &case);
+        env.storage().persistent().remove(&DataKey::OpenCase(case.target.clone()));
+        env.events().publish((symbol_short!("verdict"), case_id), case.status);
+        case
+    }
+
+    // Draw jurors with at least min_stake, excluding the opener, from up to MAX_DRAW_POOL members
+    // starting at a random slot. The seed comes from ledger data, which validators can influence
+    // slightly; it keeps jurors from choosing their own cases.
+    fn draw_jury(env: &Env, case_id: u64, target: &Target, opened_by: &Address, config: &JuryConfig) -> Vec<Address> {
+        let mut seed = Bytes::new(env);
+        seed.extend_from_array(&env.ledger().sequence().to_be_bytes());
+        seed.extend_from_array(&env.ledger().timestamp().to_be_bytes());
+        seed.extend_from_array(&case_id.to_be_bytes());
+        seed.append(&target.clone().to_xdr(env));
+        let mut hash: BytesN<32> = env.crypto().sha256(&seed).into();
+
+        let count = Self::get_juror_count(env.clone());
+        let start = Self::pick(&hash, count);
+        let end = start + count.min(MAX_DRAW_POOL);
+        let mut candidates = Self::juror_range(env, start, end);
+        if end > count {
+            candidates.append(&Self::juror_range(env, 0, end - count));
+        }
+        let mut pool = vec![env];
+        for juror in candidates.iter() {
+            if juror != *opened_by && Self::get_juror(env.clone(), juror.clone()).stake >= config.min_stake {
+                pool.push_back(juror);
+            }
+        }
+        if pool.len() < config.jury_size {
+            panic_with_error!(env, ModerationError::NotEnoughJurors);
+        }
+
+        let mut jury = vec![env];
+        while jury.len() < config.jury_size {
+            hash = env.crypto().sha256(&Bytes::from(hash)).into();
+            let index = Self::pick(&hash, pool.len());
+            jury.push_back(pool.get_unchecked(index));
+            pool.remove(index);
+        }
+        jury
+    }
+
+    // An index below `len` taken from the first eight bytes of `hash`
+    fn pick(hash: &BytesN<32>, len: u32) -> u32 {
+        if len == 0 {
+            return 0;
+        }
+        let mut word = [0u8; 8];
+        for (i, byte) in word.iter_mut().enumerate() {
+            *byte = hash.get_unchecked(i as u32);
+        }
+        (u64::from_be_bytes(word) % u64::from(len)) as u32
+    }
+
+    fn juror_page(env: &Env, page: u32) -> Vec<Address> {
+        env.storage().persistent().get(&DataKey::JurorPage(page)).unwrap_or(vec![env])
+    }
+
+    // Members in slots from..to, in slot order
+    fn juror_range(env: &Env, from: u32, to: u32) -> Vec<Address> {
+        let to = to.min(Self::get_juror_count(env.clone()));
+        let mut jurors = vec![env];
+        let mut slot = from;
+        while slot < to {
+            let base = slot / JUROR_PAGE * JUROR_PAGE;
+            let end = (base + JUROR_PAGE).min(to);
+            jurors.append(&Self::juror_page(env, slot / JUROR_PAGE).slice(slot - base..end - base));
+            slot = end;
+        }
+        jurors
+    }
+
+    fn add_to_pool(env: &Env, juror: &Address) {
+        let slot = Self::get_juror_count(env.clone());
+        let mut page = Self::juror_page(env, slot / JUROR_PAGE);
+        page.push_back(juror.clone());
+        env.storage().persistent().set(&DataKey::JurorPage(slot / JUROR_PAGE), &page);
+        env.storage().persistent().set(&DataKey::JurorSlot(juror.clone()), &slot);
+        env.storage().persistent().set(&DataKey::JurorCount, &(slot + 1));
+    }
+
+    // Move the last member into the leaving member's slot
+    fn remove_from_pool(env: &Env, juror: &Address) {
+        let slot_key = DataKey::JurorSlot(juror.clone());
+        let slot: u32 = match env.storage().persistent().get(&slot_key) {
+            Some(slot) => slot,
+            None => return,
+        };
+        env.storage().persistent().remove(&slot_key);
+        let last = Self::get_juror_count(env.clone()) - 1;
+        let mut last_page = Self::juror_page(env, last / JUROR_PAGE);
+        let moved = last_page.pop_back_unchecked();
+        if last_page.is_empty() {
+            env.storage().persistent().remove(&DataKey::JurorPage(last / JUROR_PAGE));
+        } else {
+            env.storage().persistent().set(&DataKey::JurorPage(last / JUROR_PAGE), &last_page);
+        }
+        if slot != last {
+            let mut page = Self::juror_page(env, slot / JUROR_PAGE);
+            page.set(slot % JUROR_PAGE, moved.clone());
+            env.storage().persistent().set(&DataKey::JurorPage(slot / JUROR_PAGE), &page);
+            env.storage().persistent().set(&DataKey::JurorSlot(moved), &slot);
+        }
+        env.storage().persistent().set(&DataKey::JurorCount, &last);
+    }
+
+    fn settle_stakes(env: &Env, case: &Case, hide: bool) {
+        let config = Self::get_jury_config(env.clone());
+        let mut majority = vec![env];
+        let mut majority_weight: i128 = 0;
+        let mut pool: i128 = case.fee;
+        for juror in case.jury.iter() {
+            let mut record = Self::get_juror(env.clone(), juror.clone());
+            let ballot: Option<Ballot> = env.storage().persistent().get(&DataKey::Vote(case.id, juror.clone()));
+            if let Some(ballot) = ballot.filter(|ballot| ballot.hide == hide) {
+                majority_weight += ballot.weight;
+                majority.push_back((juror, ballot.weight));
+            } else {
+                let penalty = record.stake * i128::from(config.slash_bps) / TOTAL_BPS;
+                record.stake -= penalty;
+                pool += penalty;
+                env.storage().persistent().set(&DataKey::Juror(juror), &record);
+            }
+        }
+
+        // The rounding remainder goes to the last majority juror
+        let mut paid: i128 = 0;
+        for (i, (juror, weight)) in majority.iter().enumerate() {
+            let mut record = Self::get_juror(env.clone(), juror.clone());
+            let reward = if i as u32 == majority.len() - 1 { pool - paid } else { pool * weight / majority_weight };
+            paid += reward;
+            record.stake += reward;
+            env.storage().persistent().set(&DataKey::Juror(juror), &record);
+        }
+    }
+
+    // A jury must fit in the draw pool and every majority needs stake to split rewards by
+    fn require_valid_config(env: &Env, config: &JuryConfig) {
+        if config.min_stake <= 0
+            || config.jury_size == 0
+            || config.jury_size > MAX_DRAW_POOL
+            || i128::from(config.slash_bps) > TOTAL_BPS
+            || config.case_fee < 0
+        {
+            panic_with_error!(env, ModerationError::InvalidConfig);
+        }
+    }
+
+    fn load_case(env: &Env, case_id: u64) -> Case {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Case(case_id))
+            .unwrap_or_else(|| panic_with_error!(env, ModerationError::CaseNotFound))
+    }
+
+    fn post_client(env: &Env) -> PostClient<'_> {
+        let post_contract: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PostContract)
+            .unwrap_or_else(|| panic_with_error!(env, ModerationError::NotInitialized));
+        PostClient::new(env, &post_contract)
+    }
+
+    fn stake_token(env: &Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&DataKey::StakeToken)
+            .unwrap_or_else(|| panic_with_error!(env, ModerationError::NotInitialized))
+    }
+
+    fn require_admin(env: &Env) {
+        let admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .unwrap_or_else(|| panic_with_error!(env, ModerationError::NotInitialized));
+        admin.require_auth();
+    }
+
+    fn require_not_paused(env: &Env) {
+        if Self::is_paused(env.clone()) {
+            panic_with_error!(env, ModerationError::Paused);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "ModerationContract_test.rs"]
+mod test;
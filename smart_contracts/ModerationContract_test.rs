@@ -0,0 +1,302 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::token::StellarAssetClient;
+use soroban_sdk::{contract, contractimpl};
+
+const HOUR: u64 = 60 * 60;
+
+// Stand-in for PostContract with settable report counts that records applied verdicts.
+// Setting reports creates the target; removing it simulates deletion or expiry.
+#[contract]
+pub struct MockPost;
+
+#[contractimpl]
+impl MockPost {
+    pub fn set_reports(env: Env, target: Target, reports: u32) {
+        env.storage().instance().set(&target, &reports);
+    }
+
+    pub fn get_moderation(env: Env, target: Target) -> ModerationState {
+        let reports = env.storage().instance().get(&target).unwrap_or(0);
+        ModerationState { reports, weight: reports, status: ModerationStatus::Visible }
+    }
+
+    pub fn remove_target(env: Env, target: Target) {
+        env.storage().instance().remove(&target);
+    }
+
+    pub fn apply_moderation(env: Env, target: Target, hidden: bool) -> ModerationState {
+        // PostContract rejects content that no longer exists
+        assert!(env.storage().instance().has(&target));
+        env.storage().instance().set(&(target.clone(), true), &hidden);
+        let status = if hidden { ModerationStatus::Hidden } else { ModerationStatus::Restored };
+        ModerationState { reports: 0, weight: 0, status }
+    }
+
+    pub fn applied(env: Env, target: Target) -> Option<bool> {
+        env.storage().instance().get(&(target, true))
+    }
+}
+
+struct Setup<'a> {
+    client: ModerationContractClient<'a>,
+    post: MockPostClient<'a>,
+    token: Address,
+}
+
+fn setup(env: &Env, jury_size: u32) -> Setup<'_> {
+    env.mock_all_auths();
+    let post = MockPostClient::new(env, &env.register_contract(None, MockPost));
+    let token = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+    let client = ModerationContractClient::new(env, &env.register_contract(None, ModerationContract));
+    let config = JuryConfig { min_stake: 100, jury_size, voting_period: 24 * HOUR, slash_bps: 1_000, case_fee: 0 };
+    client.initialize(&Address::generate(env), &post.address, &token, &config);
+    Setup { client, post, token }
+}
+
+fn staked_juror(env: &Env, s: &Setup, amount: i128) -> Address {
+    let juror = Address::generate(env);
+    StellarAssetClient::new(env, &s.token).mint(&juror, &amount);
+    s.client.stake(&juror, &amount);
+    juror
+}
+
+#[test]
+fn test_stake_and_unstake() {
+    let env = Env::default();
+    let s = setup(&env, 1);
+    let juror = staked_juror(&env, &s, 500);
+
+    assert_eq!(s.client.get_juror(&juror).stake, 500);
+    assert_eq!(s.client.get_jurors(&0, &10), vec![&env, juror.clone()]);
+    assert!(s.client.try_unstake(&juror, &600).is_err());
+
+    s.client.unstake(&juror, &500);
+    assert_eq!(s.client.get_juror(&juror).stake, 0);
+    assert_eq!(s.client.get_juror_count(), 0);
+    assert_eq!(token::Client::new(&env, &s.token).balance(&juror), 500);
+}
+
+#[test]
+fn test_open_case_draws_jury() {
+    let env = Env::default();
+    let s = setup(&env, 3);
+    let opener = staked_juror(&env, &s, 100);
+    let small = staked_juror(&env, &s, 50);
+    let mut eligible = vec![&env];
+    for _ in 0..4 {
+        eligible.push_back(staked_juror(&env, &s, 200));
+    }
+    let target = Target::Post(1);
+
+    // Only reported content can go to a jury
+    assert!(s.client.try_open_case(&opener, &target).is_err());
+    s.post.set_reports(&target, &2);
+
+    let case = s.client.open_case(&opener, &target);
+    assert_eq!(case.jury.len(), 3);
+    assert!(!case.jury.contains(&opener));
+    assert!(!case.jury.contains(&small));
+    for juror in case.jury.iter() {
+        assert!(eligible.contains(&juror));
+        assert_eq!(s.client.get_juror(&juror).active_cases, 1);
+        // Stake is locked while serving on a case
+        assert!(s.client.try_unstake(&juror, &10).is_err());
+        StellarAssetClient::new(&env, &s.token).mint(&juror, &10);
+        assert!(s.client.try_stake(&juror, &10).is_err());
+    }
+    assert!(s.client.try_open_case(&opener, &target).is_err());
+
+    let target = Target::Comment(1);
+    s.post.set_reports(&target, &1);
+    s.client.set_jury_config(&JuryConfig {
+        min_stake: 100,
+        jury_size: 6,
+        voting_period: HOUR,
+        slash_bps: 0,
+        case_fee: 0,
+    });
+    assert!(s.client.try_open_case(&opener, &target).is_err());
+}
+
+#[test]
+fn test_juror_pool_is_paged() {
+    let env = Env::default();
+    let s = setup(&env, 5);
+    let mut jurors = vec![&env];
+    for _ in 0..MAX_DRAW_POOL + 20 {
+        env.budget().reset_default();
+        jurors.push_back(staked_juror(&env, &s, 100));
+    }
+    assert_eq!(s.client.get_juror_count(), MAX_DRAW_POOL + 20);
+    assert_eq!(s.client.get_jurors(&0, &100).len(), MAX_JUROR_PAGE);
+
+    // Leaving moves the last member into the freed slot
+    s.client.unstake(&jurors.get_unchecked(3), &100);
+    s.client.unstake(&jurors.get_unchecked(JUROR_PAGE + 1), &100);
+    assert_eq!(s.client.get_juror_count(), MAX_DRAW_POOL + 18);
+    let last = jurors.get_unchecked(MAX_DRAW_POOL + 19);
+    assert_eq!(s.client.get_jurors(&3, &1), vec![&env, last]);
+    let mut pool = vec![&env];
+    let mut offset = 0;
+    while offset < s.client.get_juror_count() {
+        pool.append(&s.client.get_jurors(&offset, &MAX_JUROR_PAGE));
+        offset += MAX_JUROR_PAGE;
+    }
+    assert_eq!(pool.len(), MAX_DRAW_POOL + 18);
+    assert!(!pool.contains(jurors.get_unchecked(3)));
+    assert!(!pool.contains(jurors.get_unchecked(JUROR_PAGE + 1)));
+
+    let target = Target::Post(1);
+    s.post.set_reports(&target, &1);
+    env.budget().reset_default();
+    let case = s.client.open_case(&jurors.get_unchecked(0), &target);
+    assert_eq!(case.jury.len(), 5);
+    for juror in case.jury.iter() {
+        assert!(pool.contains(&juror));
+    }
+}
+
+#[test]
+fn test_verdict_rewards_majority_and_slashes_minority() {
+    let env = Env::default();
+    let s = setup(&env, 3);
+    let opener = Address::generate(&env);
+    let a = staked_juror(&env, &s, 1_000);
+    let b = staked_juror(&env, &s, 3_000);
+    let c = staked_juror(&env, &s, 1_000);
+    let target = Target::Post(7);
+    s.post.set_reports(&target, &3);
+    let case = s.client.open_case(&opener, &target);
+
+    s.client.vote(&a, &case.id, &true);
+    assert!(s.client.try_vote(&a, &case.id, &false).is_err());
+    assert!(s.client.try_vote(&opener, &case.id, &false).is_err());
+    assert!(s.client.try_finalize(&case.id).is_err());
+    s.client.vote(&b, &case.id, &true);
+    s.client.vote(&c, &case.id, &false);
+
+    // Every juror voted, so the case can close before the deadline
+    let case = s.client.finalize(&case.id);
+    assert_eq!(case.status, CaseStatus::Hidden);
+    assert_eq!(case.hide_weight, 4_000);
+    assert_eq!(case.keep_weight, 1_000);
+    assert_eq!(s.post.applied(&target), Some(true));
+
+    // The minority loses 10%, split between the majority jurors by vote weight
+    assert_eq!(s.client.get_juror(&c).stake, 900);
+    assert_eq!(s.client.get_juror(&a).stake, 1_025);
+    assert_eq!(s.client.get_juror(&b).stake, 3_075);
+    assert_eq!(s.client.get_juror(&a).active_cases, 0);
+    assert!(s.client.try_finalize(&case.id).is_err());
+
+    // A new case may be opened for the same target once the last one closed
+    s.client.open_case(&opener, &target);
+}
+
+#[test]
+fn test_tie_keeps_content_and_slashes_absentees() {
+    let env = Env::default();
+    let s = setup(&env, 3);
+    let opener = Address::generate(&env);
+    let jurors = [staked_juror(&env, &s, 1_000), staked_juror(&env, &s, 1_000), staked_juror(&env, &s, 1_000)];
+    let target = Target::Post(2);
+    s.post.set_reports(&target, &1);
+    let case = s.client.open_case(&opener, &target);
+
+    s.client.vote(&jurors[0], &case.id, &true);
+    s.client.vote(&jurors[1], &case.id, &false);
+    env.ledger().with_mut(|l| l.timestamp += 24 * HOUR);
+    assert!(s.client.try_vote(&jurors[2], &case.id, &true).is_err());
+
+    let case = s.client.finalize(&case.id);
+    assert_eq!(case.status, CaseStatus::Kept);
+    assert_eq!(s.post.applied(&target), Some(false));
+    assert_eq!(s.client.get_juror(&jurors[0]).stake, 900);
+    assert_eq!(s.client.get_juror(&jurors[1]).stake, 1_200);
+    assert_eq!(s.client.get_juror(&jurors[2]).stake, 900);
+}
+
+#[test]
+fn test_case_without_votes_expires() {
+    let env = Env::default();
+    let s = setup(&env, 1);
+    let juror = staked_juror(&env, &s, 100);
+    let target = Target::Comment(4);
+    s.post.set_reports(&target, &1);
+    let case = s.client.open_case(&Address::generate(&env), &target);
+
+    env.ledger().with_mut(|l| l.timestamp += 24 * HOUR);
+    let case = s.client.finalize(&case.id);
+    assert_eq!(case.status, CaseStatus::Expired);
+    assert_eq!(s.post.applied(&target), None);
+    assert_eq!(s.client.get_juror(&juror), Juror { stake: 100, active_cases: 0 });
+}
+
+#[test]
+fn test_finalize_when_target_removed() {
+    let env = Env::default();
+    let s = setup(&env, 1);
+    let juror = staked_juror(&env, &s, 100);
+    let target = Target::Post(9);
+    s.post.set_reports(&target, &1);
+    let case = s.client.open_case(&Address::generate(&env), &target);
+    s.client.vote(&juror, &case.id, &true);
+
+    // The post was deleted before the verdict landed
+    s.post.remove_target(&target);
+    let case = s.client.finalize(&case.id);
+    assert_eq!(case.status, CaseStatus::Hidden);
+    assert_eq!(s.post.applied(&target), None);
+    assert_eq!(s.client.get_juror(&juror).active_cases, 0);
+    s.client.unstake(&juror, &100);
+}
+
+#[test]
+fn test_rejects_invalid_config() {
+    let env = Env::default();
+    let s = setup(&env, 1);
+    let config = JuryConfig { min_stake: 100, jury_size: 1, voting_period: HOUR, slash_bps: 1_000, case_fee: 0 };
+
+    assert!(s.client.try_set_jury_config(&JuryConfig { min_stake: 0, ..config.clone() }).is_err());
+    assert!(s.client.try_set_jury_config(&JuryConfig { jury_size: 0, ..config.clone() }).is_err());
+    assert!(s.client.try_set_jury_config(&JuryConfig { jury_size: MAX_DRAW_POOL + 1, ..config.clone() }).is_err());
+    assert!(s.client.try_set_jury_config(&JuryConfig { slash_bps: 10_001, ..config.clone() }).is_err());
+    assert!(s.client.try_set_jury_config(&JuryConfig { case_fee: -1, ..config.clone() }).is_err());
+    s.client.set_jury_config(&config);
+    assert_eq!(s.client.get_jury_config(), config);
+}
+
+#[test]
+fn test_case_fee_goes_to_majority() {
+    let env = Env::default();
+    let s = setup(&env, 1);
+    let juror = staked_juror(&env, &s, 100);
+    let opener = Address::generate(&env);
+    StellarAssetClient::new(&env, &s.token).mint(&opener, &100);
+    s.client.set_jury_config(&JuryConfig {
+        min_stake: 100,
+        jury_size: 1,
+        voting_period: HOUR,
+        slash_bps: 1_000,
+        case_fee: 40,
+    });
+    let target = Target::Post(3);
+    s.post.set_reports(&target, &1);
+
+    // Without votes the fee goes back to the opener
+    let case = s.client.open_case(&opener, &target);
+    assert_eq!(case.fee, 40);
+    assert_eq!(token::Client::new(&env, &s.token).balance(&opener), 60);
+    env.ledger().with_mut(|l| l.timestamp += HOUR);
+    s.client.finalize(&case.id);
+    assert_eq!(token::Client::new(&env, &s.token).balance(&opener), 100);
+
+    let case = s.client.open_case(&opener, &target);
+    s.client.vote(&juror, &case.id, &false);
+    s.client.finalize(&case.id);
+    assert_eq!(s.client.get_juror(&juror).stake, 140);
+    assert_eq!(token::Client::new(&env, &s.token).balance(&opener), 60);
+}
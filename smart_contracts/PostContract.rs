@@ -1,31 +1,2241 @@
 #![no_std]
-use soroban_sdk::{contractimpl, Address, Env, Symbol, Vec, String, BytesN, Map, u64};
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, token, vec,
+    xdr::ToXdr, Address, Bytes, BytesN, Env, IntoVal, Map, String, Symbol, TryFromVal, Val, Vec,
+};
 
+// Bumped whenever stored data needs migrating after an upgrade
+const SCHEMA_VERSION: u32 = 1;
+
+// Upper bounds for a single get_post_comments call
+const MAX_THREAD_DEPTH: u32 = 8;
+const MAX_THREAD_COMMENTS: u32 = 100;
+
+// Lists that grow with use are stored in pages of LIST_PAGE slots, so no single entry grows without
+// bound. Removing an item empties its slot; the slots of later items never move.
+const LIST_PAGE: u32 = 50;
+
+// Upper bound for a single get_reactors call
+const MAX_REACTOR_PAGE: u32 = 50;
+
+// Distinct (tipper, token) pairs an anonymous post can hold in escrow, bounding the refund at expiry
+const MAX_ESCROW_TIPPERS: u32 = 50;
+
+// Upper bound for a single get_bookmarks call
+const MAX_BOOKMARK_PAGE: u32 = 50;
+
+// Upper bound for a single get_user_posts or search_posts call
+const MAX_POST_PAGE: u32 = 50;
+
+// How long after posting the author may still edit, unless the admin changes it
+const DEFAULT_EDIT_WINDOW: u64 = 60 * 60;
+// Most versions a post keeps, the original included; further edits are rejected
+const MAX_REVISIONS: u32 = 10;
+
+// Feed indexes keep only their best-ranked entries. At about 100 bytes an entry, a full index
+// is some 10KB, so a 7-day Top read (eight day buckets) stays well inside a transaction's read limit.
+const FEED_INDEX_CAP: u32 = 100;
+const MAX_FEED_PAGE: u32 = 50;
+// Upper bound on index entries a single get_feed call walks through
+const MAX_FEED_SCAN: u32 = 200;
+// The top index is split by creation day, so a Top window only reads the days it covers
+const TOP_BUCKET: u64 = 24 * 60 * 60;
+const MAX_TOP_WINDOW: u64 = 7 * 24 * 60 * 60;
+
+// Hot ranking: each doubling of likes is worth HOT_HALF_LIFE seconds of recency.
+// Scores never change with time, so older posts decay relative to newer ones without rescoring.
+const HOT_HALF_LIFE: u64 = 12 * 60 * 60;
+const HOT_SCALE: u64 = 1_000;
+
+// Tag activity is counted in hourly buckets kept in temporary storage for MAX_TRENDING_WINDOW
+const TAG_BUCKET: u64 = 60 * 60;
+const MAX_TRENDING_WINDOW: u64 = 7 * 24 * 60 * 60;
+const LEDGER_SECONDS: u64 = 5;
+const MAX_TRENDING_TAGS: u32 = 50;
+// A new post counts as much as this many reactions or comments
+const TAG_POST_WEIGHT: u32 = 5;
+
+// Distinct users whose engagement with a post is tracked. Reactions from users past the cap still
+// count toward the post's reactions but not toward tag activity or LikeReceived.
+const ENGAGED_CAP: u32 = 100;
+
+// Weighted reports needed to hide content, unless the admin changes it
+const DEFAULT_REPORT_THRESHOLD: u32 = 5;
+
+// Longest an expiring post may live, and how long its entry outlives expiry so purge_expired
+// can still clean up the indexes
+const MAX_POST_LIFETIME: u64 = 30 * 24 * 60 * 60;
+const PURGE_GRACE: u64 = 24 * 60 * 60;
+// Expiring posts are indexed by expiry day. A purge_expired call reads or purges at most
+// MAX_PURGE buckets and posts in total.
+const EXPIRY_BUCKET: u64 = 24 * 60 * 60;
+const MAX_PURGE: u32 = 20;
+
+// Bounds on the options of a poll
+const MIN_POLL_OPTIONS: u32 = 2;
+const MAX_POLL_OPTIONS: u32 = 10;
+// Time after a sealed poll closes during which committed votes can be revealed
+const POLL_REVEAL_WINDOW: u64 = 24 * 60 * 60;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum PostError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    Paused = 3,
+    UnknownSchemaVersion = 4,
+    PostNotFound = 5,
+    NotAuthor = 6,
+    CommentNotFound = 7,
+    InvalidReaction = 8,
+    EditWindowClosed = 9,
+    AlreadyReported = 10,
+    NotModerator = 11,
+    NotAppealable = 12,
+    InvalidAmount = 13,
+    InvalidPoll = 14,
+    NotAPoll = 15,
+    PollClosed = 16,
+    PollOpen = 17,
+    AlreadyVoted = 18,
+    InvalidReveal = 19,
+    AlreadyReposted = 20,
+    InvalidExpiry = 21,
+    InvalidVisibility = 22,
+    AlreadyReacted = 23,
+    TooManyRevisions = 24,
+    TooManyTippers = 25,
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub enum DataKey {
+    Admin,
+    Paused,
+    SchemaVersion,
+    AnalyticsContract,
+    PostCount,
+    Post(u64),           // persistent, or temporary for posts with an expiry
+    ListLen(List),       // slots used by a list, removed items included
+    ListPage(List, u32), // Map<u32, item> by slot within the page
+    ListSlot(List, u64), // slot of an id in an id list
+    CommentCount,
+    Comment(u64),
+    ReactionSet,
+    Reaction(Target, Address),
+    AnonReaction(Target, BytesN<32>), // keyed by reaction_commitment
+    ReactorSlot(Target, ReactorKey),  // slot in List::Reactors
+    Engaged(u64), // Map<Address, bool>: users whose first reaction on a post counted, and whether they liked it
+    EditWindow,
+    Revisions(u64),
+    FeedNewest,
+    FeedTop(u64), // Vec<FeedEntry> for posts created in one TOP_BUCKET
+    FeedHot,
+    TagCount(Symbol),
+    TagBucket(u64), // Map<Symbol, TagStats> for one TAG_BUCKET, in temporary storage
+    Moderators,
+    ReportThreshold,
+    ReporterWeight(Address),
+    Report(Target, Address),
+    Moderation(Target),
+    Appeals,
+    ModerationContract,
+    Tips(u64),                        // Map<token, i128> of everything tipped to a post
+    Escrow(u64),                      // Map<token, i128> held for the author of an anonymous post
+    EscrowTip(u64, Address, Address), // i128 a tipper put into Escrow in one token, refunded if unclaimed
+    Poll(u64),
+    PollTally(u64), // Vec<u32> of votes per option, readable once the poll closes
+    Ballot(u64, Address),
+    BookmarkLists(Address),
+    Reposted(u64, Address), // id of a user's plain repost of a post
+    Expiring(u64),          // Map<u64, ExpiringPost> of posts expiring in one EXPIRY_BUCKET
+    PurgeFrom,              // earliest EXPIRY_BUCKET that may still hold posts to purge
+    FollowContract,
+    MatchContract,
+}
+
+// What purge_expired needs to clean up after a post whose temporary entry may already be gone
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpiringPost {
+    pub expires_at: u64,
+    pub author: Option<Address>,
+    pub tags: Vec<Symbol>,
+    pub timestamp: u64,
+    pub repost_of: Option<u64>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Report {
+    pub reason: Symbol,
+    pub weight: u32,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ModerationStatus {
+    Visible,
+    Hidden,   // crossed the report threshold
+    Appealed, // hidden, waiting for a moderator
+    Restored, // a moderator overturned the reports; further reports cannot hide it again
+    Upheld,   // a moderator confirmed the hide; no further appeals
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModerationState {
+    pub reports: u32,
+    pub weight: u32,
+    pub status: ModerationStatus,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TagStats {
+    pub posts: u32,
+    pub engagement: u32, // reactions and comments on posts with the tag
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TagTrend {
+    pub tag: Symbol,
+    pub posts: u32,
+    pub engagement: u32,
+    pub score: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FeedMode {
+    Newest,
+    Top(u64), // most liked posts created within the last N seconds, up to MAX_TOP_WINDOW
+    Hot,
+}
+
+// One position in a feed index, ordered by (score, id) descending
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeedEntry {
+    pub id: u64,
+    pub score: u64,
+    pub created_at: u64,
+}
+
+// Pass back to get_feed to continue after the last post of a page
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeedCursor {
+    pub score: u64,
+    pub id: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Revision {
+    pub content: String,
+    pub timestamp: u64,
+}
+
+// A post or comment
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Target {
+    Post(u64),
+    Comment(u64),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserReaction {
+    pub reaction: Symbol,
+    pub anonymous: bool,
+}
+
+// Lists stored through DataKey::ListPage, oldest item first
+#[contracttype]
 #[derive(Clone)]
+pub enum List {
+    UserPosts(Address),
+    TagPosts(Symbol),
+    PostComments(u64),   // top-level comment ids of a post
+    CommentReplies(u64), // direct reply ids of a comment
+    Reactors(Target),    // ReactorKey in reaction order
+    Bookmarks(Address, Symbol),
+    EscrowTippers(u64), // (tipper, token) behind the escrowed tips of a post
+}
+
+// Who holds an entry in a target's reactor list
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReactorKey {
+    User(Address),
+    Anonymous(BytesN<32>),
+}
+
+// One entry of get_reactors. `user` is None for anonymous reactions.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reactor {
+    pub user: Option<Address>,
+    pub reaction: Symbol,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Post {
     pub id: u64,
     pub content: String,
     pub timestamp: u64,
-    pub author: Address,
+    pub author: Option<Address>, // None for anonymous posts until the author claims them
+    pub author_commitment: Option<Bytes>, // 32-byte sha256(author xdr || salt) for anonymous posts
+    pub anonymous: bool,
+    pub tags: Vec<Symbol>,
+    pub likes: u32, // same as reactions[like]
+    pub comments: u32,
+    pub reactions: Map<Symbol, u32>,
+    pub edited: bool,            // content is the latest revision
+    pub hidden: bool,            // hidden by moderation; left out of feeds, searches and listings
+    pub poll: bool,              // options and voting live under get_poll
+    pub repost_of: Option<u64>,  // plain reposts have empty content, quote posts their own
+    pub reposts: u32,            // reposts and quotes of this post
+    pub deleted: bool,           // tombstone kept so reposts still resolve
+    pub expires_at: Option<u64>, // expired posts are no longer readable
+    pub visibility: Visibility,
+    pub ciphertext: Option<Bytes>, // content of non-public posts, encrypted off-chain; `content` stays empty
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Visibility {
+    Public,
+    Followers, // the author and their followers
+    Matches,   // the author and their matches
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Poll {
+    pub options: Vec<String>,
+    pub closes_at: u64,
+    pub sealed: bool, // votes are committed while open and revealed after closing
+    pub voters: u32,  // addresses that voted or committed a vote
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Ballot {
+    Committed(BytesN<32>), // see `vote_commitment`
+    Cast(u32),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Comment {
+    pub id: u64,
+    pub post_id: u64,
+    pub parent_comment_id: Option<u64>,
+    pub author: Option<Address>,          // None for anonymous comments
+    pub author_commitment: Option<Bytes>, // 32-byte author_commitment for anonymous comments
+    pub content: String,
+    pub timestamp: u64,
+    pub anonymous: bool,
+    pub replies: u32,  // direct replies, including tombstoned ones
+    pub deleted: bool, // tombstone kept so replies stay attached
+    pub depth: u32,    // 0 for top-level comments
+    pub reactions: Map<Symbol, u32>,
+    pub hidden: bool, // hidden by moderation, together with its replies
+}
+
+// The UserProfileContract::AnalyticsEvent variants this contract reports
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AnalyticsEvent {
+    LikeReceived,
+}
+
+// The subset of UserProfileContract used to report profile activity
+#[contractclient(name = "AnalyticsClient")]
+pub trait AnalyticsInterface {
+    fn record_event(env: Env, reporter: Address, user: Address, event: AnalyticsEvent);
+}
+
+// The subset of a follow graph contract used to check followers-only posts
+#[contractclient(name = "FollowClient")]
+pub trait FollowInterface {
+    fn is_following(env: Env, follower: Address, followee: Address) -> bool;
+}
+
+// The subset of a matching contract used to check matches-only posts
+#[contractclient(name = "MatchClient")]
+pub trait MatchInterface {
+    fn is_matched(env: Env, user: Address, other: Address) -> bool;
+}
+
+// Commitment binding an anonymous post to its author. Compute this off-chain
+// with a fresh random salt; publishing the salt later proves authorship.
+pub fn author_commitment(env: &Env, author: &Address, salt: &BytesN<32>) -> BytesN<32> {
+    let mut preimage: Bytes = author.clone().to_xdr(env);
+    preimage.append(&Bytes::from(salt.clone()));
+    env.crypto().sha256(&preimage).into()
+}
+
+// Commitment hiding a vote in a sealed poll until it is revealed. Compute this
+// off-chain with a fresh random salt.
+pub fn vote_commitment(env: &Env, voter: &Address, option: u32, salt: &BytesN<32>) -> BytesN<32> {
+    let mut preimage: Bytes = voter.clone().to_xdr(env);
+    preimage.extend_from_array(&option.to_be_bytes());
+    preimage.append(&Bytes::from(salt.clone()));
+    env.crypto().sha256(&preimage).into()
+}
+
+// Commitment keying an anonymous reaction. Compute this off-chain from a random secret kept
+// by the reactor, never from their address; revealing the secret removes the reaction.
+pub fn reaction_commitment(env: &Env, target: &Target, secret: &BytesN<32>) -> BytesN<32> {
+    let mut preimage: Bytes = target.clone().to_xdr(env);
+    preimage.append(&Bytes::from(secret.clone()));
+    env.crypto().sha256(&preimage).into()
 }
 
+#[contract]
 pub struct PostContract;
 
 #[contractimpl]
 impl PostContract {
-    pub fn create_post(env: Env, author: Address, content: String, timestamp: u64) {
-        let id = env.storage().get::<_, u64>(&Symbol::short("post_id")).unwrap_or(0) + 1;
-        let post = Post { id, content, timestamp, author };
-        env.storage().set(&id, &post);
-        env.storage().set(&Symbol::short("post_id"), &id);
+    pub fn initialize(env: Env, admin: Address) {
+        if env.storage().instance().has(&DataKey::Admin) {
+            panic_with_error!(&env, PostError::AlreadyInitialized);
+        }
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+        env.storage().instance().set(&DataKey::ReactionSet, &Self::default_reactions(&env));
+    }
+
+    // Pause or resume posting. Reads keep working.
+    pub fn set_paused(env: Env, paused: bool) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    // Swap in new code. Posts, comments and indexes stay in storage until `migrate` updates them.
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
+        Self::require_admin(&env);
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+    }
+
+    // Bring stored data up to the current schema version
+    pub fn migrate(env: Env) -> u32 {
+        Self::require_admin(&env);
+        let version = Self::get_schema_version(env.clone());
+        if version > SCHEMA_VERSION {
+            panic_with_error!(&env, PostError::UnknownSchemaVersion);
+        }
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+        SCHEMA_VERSION
+    }
+
+    pub fn get_schema_version(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::SchemaVersion).unwrap_or(0)
+    }
+
+    // Report likes on public posts to UserProfileContract. This contract must be registered there as a reporter.
+    pub fn set_analytics_contract(env: Env, analytics_contract: Address) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::AnalyticsContract, &analytics_contract);
+    }
+
+    // Contract answering `is_following` for followers-only posts
+    pub fn set_follow_contract(env: Env, follow_contract: Address) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::FollowContract, &follow_contract);
+    }
+
+    // Contract answering `is_matched` for matches-only posts, e.g. UserProfileContract
+    pub fn set_match_contract(env: Env, match_contract: Address) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::MatchContract, &match_contract);
+    }
+
+    // Seconds after posting during which edit_post is allowed
+    pub fn set_edit_window(env: Env, seconds: u64) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::EditWindow, &seconds);
+    }
+
+    pub fn get_edit_window(env: Env) -> u64 {
+        env.storage().instance().get(&DataKey::EditWindow).unwrap_or(DEFAULT_EDIT_WINDOW)
+    }
+
+    pub fn create_post(env: Env, author: Address, content: String, tags: Vec<Symbol>) -> Post {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        let post = Self::store_new_post(&env, Some(author.clone()), None, content, tags, false, None);
+        Self::push_id(&env, List::UserPosts(author), post.id);
+        post
+    }
+
+    // Create a post only the author's followers or matches can read through the contract. Everything on
+    // the ledger is public, so the content must already be encrypted for its audience.
+    pub fn create_scoped_post(
+        env: Env,
+        author: Address,
+        ciphertext: Bytes,
+        tags: Vec<Symbol>,
+        visibility: Visibility,
+    ) -> Post {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        if visibility == Visibility::Public {
+            panic_with_error!(&env, PostError::InvalidVisibility);
+        }
+
+        let mut post =
+            Self::store_new_post(&env, Some(author.clone()), None, String::from_str(&env, ""), tags, false, None);
+        post.visibility = visibility;
+        post.ciphertext = Some(ciphertext);
+        Self::write_post(&env, &post);
+        Self::push_id(&env, List::UserPosts(author), post.id);
+        post
+    }
+
+    // Create a post with 2-10 options that accepts votes until `closes_at`. Votes on a sealed poll
+    // are committed with `commit_poll_vote` and revealed after it closes.
+    pub fn create_poll(
+        env: Env,
+        author: Address,
+        content: String,
+        tags: Vec<Symbol>,
+        options: Vec<String>,
+        closes_at: u64,
+        sealed: bool,
+    ) -> Post {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        if options.len() < MIN_POLL_OPTIONS || options.len() > MAX_POLL_OPTIONS || closes_at <= env.ledger().timestamp()
+        {
+            panic_with_error!(&env, PostError::InvalidPoll);
+        }
+
+        let post = Self::store_new_post(&env, Some(author.clone()), None, content, tags, true, None);
+        let mut tally = vec![&env];
+        for _ in 0..options.len() {
+            tally.push_back(0u32);
+        }
+        env.storage().persistent().set(&DataKey::Poll(post.id), &Poll { options, closes_at, sealed, voters: 0 });
+        env.storage().persistent().set(&DataKey::PollTally(post.id), &tally);
+        Self::push_id(&env, List::UserPosts(author), post.id);
+        post
+    }
+
+    // Create a post that stores only a commitment to its author, never the address.
+    // See `author_commitment` for how the commitment is built.
+    pub fn create_anonymous_post(env: Env, author_commitment: BytesN<32>, content: String, tags: Vec<Symbol>) -> Post {
+        Self::require_not_paused(&env);
+        Self::store_new_post(&env, None, Some(author_commitment), content, tags, false, None)
+    }
+
+    // Publicly claim an anonymous post by revealing the salt behind its commitment
+    pub fn claim_post(env: Env, id: u64, author: Address, salt: BytesN<32>) -> Post {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        let mut post = Self::load_post(&env, id);
+        if post.author.is_some() || !Self::opens_commitment(&env, &post.author_commitment, &author, &Some(salt)) {
+            panic_with_error!(&env, PostError::NotAuthor);
+        }
+
+        post.author = Some(author.clone());
+        Self::write_post(&env, &post);
+        Self::push_id(&env, List::UserPosts(author), id);
+        if post.expires_at.is_some() {
+            Self::index_expiry(&env, &post);
+        }
+        post
+    }
+
+    // Share a post on the user's own timeline. Each user can repost a post once.
+    pub fn repost(env: Env, post_id: u64, author: Address) -> Post {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        let key = DataKey::Reposted(post_id, author.clone());
+        if env.storage().persistent().has(&key) {
+            panic_with_error!(&env, PostError::AlreadyReposted);
+        }
+        let post = Self::store_repost(&env, post_id, &author, String::from_str(&env, ""));
+        env.storage().persistent().set(&key, &post.id);
+        post
+    }
+
+    // Share a post with a comment of the user's own
+    pub fn quote_post(env: Env, post_id: u64, author: Address, content: String) -> Post {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        Self::store_repost(&env, post_id, &author, content)
+    }
+
+    // Read a post. Deleted posts that were reposted come back as tombstones with `deleted` set.
+    // Non-public posts are only returned to viewers in their scope.
+    pub fn get_post(env: Env, id: u64, viewer: Option<Address>) -> Option<Post> {
+        Self::live_post(&env, id).filter(|post| Self::can_view(&env, post, &viewer))
+    }
+
+    // Ranked posts, `limit` at a time, plus the cursor for the next page (None once the feed is exhausted).
+    // Start with no cursor and pass each returned cursor back to continue. A call walks at most
+    // MAX_FEED_SCAN index entries, counting scoped posts that need a follow or match lookup twice,
+    // so a page may come back short with a cursor to resume from.
+    pub fn get_feed(
+        env: Env,
+        mode: FeedMode,
+        viewer: Option<Address>,
+        cursor: Option<FeedCursor>,
+        limit: u32,
+    ) -> (Vec<Post>, Option<FeedCursor>) {
+        let now = env.ledger().timestamp();
+        let mut keys = vec![&env];
+        let mut created_after = 0;
+        match mode {
+            FeedMode::Newest => keys.push_back(DataKey::FeedNewest),
+            FeedMode::Top(window) => {
+                created_after = now.saturating_sub(window.min(MAX_TOP_WINDOW));
+                for bucket in created_after / TOP_BUCKET..=now / TOP_BUCKET {
+                    keys.push_back(DataKey::FeedTop(bucket));
+                }
+            }
+            FeedMode::Hot => keys.push_back(DataKey::FeedHot),
+        }
+
+        // Merge the indexes, each read from the cursor onwards
+        let mut indexes: Vec<Vec<FeedEntry>> = vec![&env];
+        let mut heads: Vec<u32> = vec![&env];
+        for key in keys.iter() {
+            let entries: Vec<FeedEntry> = env.storage().persistent().get(&key).unwrap_or(vec![&env]);
+            heads.push_back(cursor.as_ref().map_or(0, |c| Self::feed_position(&entries, c.score, c.id)));
+            indexes.push_back(entries);
+        }
+
+        let limit = limit.min(MAX_FEED_PAGE);
+        let mut posts = vec![&env];
+        let mut next_cursor = cursor;
+        let mut scanned = 0;
+        while posts.len() < limit && scanned < MAX_FEED_SCAN {
+            let Some((index, entry)) = Self::next_feed_entry(&indexes, &heads) else {
+                return (posts, None);
+            };
+            let post = Self::visible_post(&env, entry.id).filter(|_| entry.created_at >= created_after);
+            // A scope check that calls the follow or match contract counts as another scanned entry
+            let cost = if post.as_ref().is_some_and(|post| Self::needs_scope_lookup(post, &viewer)) { 2 } else { 1 };
+            if scanned + cost > MAX_FEED_SCAN {
+                break;
+            }
+            heads.set(index, heads.get_unchecked(index) + 1);
+            scanned += cost;
+            next_cursor = Some(FeedCursor { score: entry.score, id: entry.id });
+            if let Some(post) = post.filter(|post| Self::can_view(&env, post, &viewer)) {
+                posts.push_back(post);
+            }
+        }
+        if Self::next_feed_entry(&indexes, &heads).is_none() {
+            next_cursor = None;
+        }
+        (posts, next_cursor)
+    }
+
+    // Posts published under the user's address, oldest first. Unclaimed anonymous posts are never listed.
+    pub fn get_user_posts(env: Env, author: Address, viewer: Option<Address>, offset: u32, limit: u32) -> Vec<Post> {
+        Self::load_posts(&env, List::UserPosts(author), &viewer, offset, limit)
+    }
+
+    // Posts carrying the tag, oldest first
+    pub fn search_posts(env: Env, tag: Symbol, viewer: Option<Address>, offset: u32, limit: u32) -> Vec<Post> {
+        Self::load_posts(&env, List::TagPosts(tag), &viewer, offset, limit)
+    }
+
+    // Live posts carrying the tag
+    pub fn get_tag_post_count(env: Env, tag: Symbol) -> u32 {
+        env.storage().persistent().get(&DataKey::TagCount(tag)).unwrap_or(0)
+    }
+
+    // Most active tags over the last `window` seconds (at most seven days), best first.
+    // Scores weigh new posts by TAG_POST_WEIGHT against reactions and comments.
+    pub fn trending_tags(env: Env, window: u64, limit: u32) -> Vec<TagTrend> {
+        let now = env.ledger().timestamp();
+        let first = now.saturating_sub(window.min(MAX_TRENDING_WINDOW)) / TAG_BUCKET;
+        let mut totals: Map<Symbol, TagStats> = Map::new(&env);
+        for bucket in first..=now / TAG_BUCKET {
+            let Some(stats) = env.storage().temporary().get::<_, Map<Symbol, TagStats>>(&DataKey::TagBucket(bucket))
+            else {
+                continue;
+            };
+            for (tag, bucket_stats) in stats.iter() {
+                let mut total = totals.get(tag.clone()).unwrap_or_default();
+                total.posts += bucket_stats.posts;
+                total.engagement += bucket_stats.engagement;
+                totals.set(tag, total);
+            }
+        }
+
+        let limit = limit.min(MAX_TRENDING_TAGS);
+        let mut trends: Vec<TagTrend> = vec![&env];
+        for (tag, total) in totals.iter() {
+            let score = total.posts * TAG_POST_WEIGHT + total.engagement;
+            let index = trends.iter().position(|t| t.score < score).unwrap_or(trends.len() as usize) as u32;
+            if index < limit {
+                trends.insert(index, TagTrend { tag, posts: total.posts, engagement: total.engagement, score });
+                if trends.len() > limit {
+                    trends.pop_back();
+                }
+            }
+        }
+        trends
+    }
+
+    // Shorthand for reacting to a post with `like`. Returns the post as get_post shows it to the liker.
+    pub fn like_post(env: Env, id: u64, liker: Address) -> Option<Post> {
+        Self::react(env.clone(), Target::Post(id), liker.clone(), symbol_short!("like"));
+        Self::get_post(env, id, Some(liker))
+    }
+
+    // Remove the liker's `like`, leaving any other reaction in place
+    pub fn unlike_post(env: Env, id: u64, liker: Address) -> Option<Post> {
+        Self::require_not_paused(&env);
+        liker.require_auth();
+        let key = DataKey::Reaction(Target::Post(id), liker.clone());
+        let current = env.storage().persistent().get::<_, UserReaction>(&key);
+        if current.is_some_and(|r| r.reaction == symbol_short!("like")) {
+            Self::remove_reaction(&env, &Target::Post(id), key, ReactorKey::User(liker.clone()));
+        }
+        Self::get_post(env, id, Some(liker))
+    }
+
+    pub fn add_reaction_type(env: Env, reaction: Symbol) {
+        Self::require_admin(&env);
+        let mut reactions = Self::get_reaction_types(env.clone());
+        if !reactions.contains(&reaction) {
+            reactions.push_back(reaction);
+            env.storage().instance().set(&DataKey::ReactionSet, &reactions);
+        }
+    }
+
+    pub fn get_reaction_types(env: Env) -> Vec<Symbol> {
+        env.storage().instance().get(&DataKey::ReactionSet).unwrap_or(Self::default_reactions(&env))
+    }
+
+    // React to a post or comment. Each user holds at most one public reaction per target;
+    // reacting again replaces it. See `react_anonymously` for reactions without an address.
+    pub fn react(env: Env, target: Target, user: Address, reaction: Symbol) -> Map<Symbol, u32> {
+        Self::require_not_paused(&env);
+        user.require_auth();
+        Self::require_reaction_type(&env, &reaction);
+        Self::require_target(&env, &target, &Some(user.clone()));
+
+        let key = DataKey::Reaction(target.clone(), user.clone());
+        let previous = env.storage().persistent().get::<_, UserReaction>(&key);
+        env.storage().persistent().set(&key, &UserReaction { reaction: reaction.clone(), anonymous: false });
+        match previous {
+            Some(previous) if previous.reaction == reaction => return Self::load_reactions(&env, &target),
+            Some(previous) => {
+                Self::count_reaction(&env, &target, &previous.reaction, false);
+            }
+            None => Self::push_reactor(&env, &target, ReactorKey::User(user.clone())),
+        }
+
+        let counts = Self::count_reaction(&env, &target, &reaction, true);
+        if let Target::Post(id) = target {
+            Self::record_engagement(&env, id, user, &reaction);
+        }
+        counts
+    }
+
+    // React to a public post or comment without an address. The reaction is stored under
+    // `commitment` (see `reaction_commitment`), counted, and listed by get_reactors without a user.
+    // A commitment holds one reaction; changing it takes unreact_anonymously first.
+    pub fn react_anonymously(env: Env, target: Target, commitment: BytesN<32>, reaction: Symbol) -> Map<Symbol, u32> {
+        Self::require_not_paused(&env);
+        Self::require_reaction_type(&env, &reaction);
+        Self::require_target(&env, &target, &None);
+
+        let key = DataKey::AnonReaction(target.clone(), commitment.clone());
+        if env.storage().persistent().has(&key) {
+            panic_with_error!(&env, PostError::AlreadyReacted);
+        }
+        env.storage().persistent().set(&key, &UserReaction { reaction: reaction.clone(), anonymous: true });
+        Self::push_reactor(&env, &target, ReactorKey::Anonymous(commitment));
+        Self::count_reaction(&env, &target, &reaction, true)
+    }
+
+    // Remove the user's public reaction
+    pub fn unreact(env: Env, target: Target, user: Address) -> Map<Symbol, u32> {
+        Self::require_not_paused(&env);
+        user.require_auth();
+        let key = DataKey::Reaction(target.clone(), user.clone());
+        Self::remove_reaction(&env, &target, key, ReactorKey::User(user))
+    }
+
+    // Remove an anonymous reaction by revealing the secret behind its commitment
+    pub fn unreact_anonymously(env: Env, target: Target, secret: BytesN<32>) -> Map<Symbol, u32> {
+        Self::require_not_paused(&env);
+        let commitment = reaction_commitment(&env, &target, &secret);
+        let key = DataKey::AnonReaction(target.clone(), commitment.clone());
+        Self::remove_reaction(&env, &target, key, ReactorKey::Anonymous(commitment))
+    }
+
+    // The user's public reaction to a target
+    pub fn get_reaction(env: Env, target: Target, user: Address) -> Option<UserReaction> {
+        env.storage().persistent().get(&DataKey::Reaction(target, user))
+    }
+
+    // The anonymous reaction stored under a commitment
+    pub fn get_anonymous_reaction(env: Env, target: Target, commitment: BytesN<32>) -> Option<UserReaction> {
+        env.storage().persistent().get(&DataKey::AnonReaction(target, commitment))
+    }
+
+    // Who reacted to a target, in reaction order. Anonymous reactors are listed without their address.
+    // `offset` counts slots, and withdrawn reactions leave theirs empty, so a page may hold fewer than `limit`.
+    pub fn get_reactors(env: Env, target: Target, offset: u32, limit: u32) -> Vec<Reactor> {
+        let list = List::Reactors(target.clone());
+        let reactors: Vec<ReactorKey> =
+            Self::list_range(&env, &list, offset, offset.saturating_add(limit.min(MAX_REACTOR_PAGE)));
+
+        let mut page = vec![&env];
+        for reactor in reactors.iter() {
+            let (key, user) = match reactor {
+                ReactorKey::User(user) => (DataKey::Reaction(target.clone(), user.clone()), Some(user)),
+                ReactorKey::Anonymous(commitment) => (DataKey::AnonReaction(target.clone(), commitment), None),
+            };
+            if let Some(r) = env.storage().persistent().get::<_, UserReaction>(&key) {
+                page.push_back(Reactor { user, reaction: r.reaction });
+            }
+        }
+        page
+    }
+
+    // Comment on a post, or reply to one of its comments when `parent_comment_id` is set.
+    // See `add_anonymous_comment` for comments without an address.
+    pub fn add_comment(
+        env: Env,
+        post_id: u64,
+        author: Address,
+        content: String,
+        parent_comment_id: Option<u64>,
+    ) -> Comment {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        Self::require_target(&env, &Target::Post(post_id), &Some(author.clone()));
+        let post = Self::load_post(&env, post_id);
+        Self::store_comment(&env, post, Some(author), None, content, parent_comment_id)
+    }
+
+    // Comment on a public post without an address. `author_commitment` is computed off-chain as
+    // for anonymous posts, and checked against author_commitment(author, salt) on deletion.
+    pub fn add_anonymous_comment(
+        env: Env,
+        post_id: u64,
+        author_commitment: BytesN<32>,
+        content: String,
+        parent_comment_id: Option<u64>,
+    ) -> Comment {
+        Self::require_not_paused(&env);
+        Self::require_target(&env, &Target::Post(post_id), &None);
+        let post = Self::load_post(&env, post_id);
+        Self::store_comment(&env, post, None, Some(author_commitment), content, parent_comment_id)
+    }
+
+    // A comment, if the viewer can see the post it belongs to
+    pub fn get_comment(env: Env, id: u64, viewer: Option<Address>) -> Option<Comment> {
+        let comment: Comment = env.storage().persistent().get(&DataKey::Comment(id))?;
+        Self::viewable_post(&env, comment.post_id, &viewer)?;
+        Some(comment)
+    }
+
+    // The replies to `parent_comment_id` (top-level comments when None), each followed by its own
+    // replies, depth-first, down to `max_depth` levels below the parent. Returns `limit` comments at
+    // most, up to MAX_THREAD_COMMENTS, plus the id to pass back as `cursor` for the next page (None on
+    // the last page). Empty when the viewer cannot see the post.
+    pub fn get_post_comments(
+        env: Env,
+        post_id: u64,
+        viewer: Option<Address>,
+        parent_comment_id: Option<u64>,
+        max_depth: u32,
+        cursor: Option<u64>,
+        limit: u32,
+    ) -> (Vec<Comment>, Option<u64>) {
+        if Self::viewable_post(&env, post_id, &viewer).is_none() {
+            return (vec![&env], None);
+        }
+        let base_depth = match parent_comment_id {
+            Some(parent_id) => {
+                let parent = Self::load_comment(&env, parent_id);
+                if parent.post_id != post_id {
+                    panic_with_error!(&env, PostError::CommentNotFound);
+                }
+                parent.depth + 1
+            }
+            None => 0,
+        };
+        let max_depth = max_depth.min(MAX_THREAD_DEPTH);
+        let limit = limit.min(MAX_THREAD_COMMENTS);
+
+        // The reply lists being walked, innermost last, with the slot to continue each one from
+        let mut open = match cursor {
+            None => vec![&env, (parent_comment_id, 0)],
+            Some(id) => Self::resume_thread(&env, post_id, parent_comment_id, id),
+        };
+
+        let mut comments = vec![&env];
+        while let Some((parent, slot)) = open.pop_back() {
+            let Some((slot, id)) = Self::list_next::<u64>(&env, &Self::thread_list(post_id, parent), slot) else {
+                continue;
+            };
+            if comments.len() == limit {
+                return (comments, Some(id));
+            }
+            open.push_back((parent, slot + 1));
+            let Some(comment) = env.storage().persistent().get::<_, Comment>(&DataKey::Comment(id)) else {
+                continue;
+            };
+            if comment.hidden {
+                continue;
+            }
+            if comment.replies > 0 && comment.depth - base_depth + 1 < max_depth {
+                open.push_back((Some(id), 0));
+            }
+            comments.push_back(comment);
+        }
+        (comments, None)
+    }
+
+    // Delete a comment. Comments with replies are tombstoned so the thread stays intact.
+    // Anonymous comments require the salt behind their author commitment.
+    pub fn delete_comment(env: Env, id: u64, author: Address, salt: Option<BytesN<32>>) {
+        Self::require_not_paused(&env);
+        let mut comment = Self::load_comment(&env, id);
+        Self::require_comment_author(&env, &comment, &author, &salt);
+        if comment.deleted {
+            panic_with_error!(&env, PostError::NotAuthor);
+        }
+
+        if comment.replies > 0 {
+            comment.deleted = true;
+            comment.content = String::from_str(&env, "");
+            comment.author = None;
+            comment.author_commitment = None;
+            env.storage().persistent().set(&DataKey::Comment(id), &comment);
+        } else {
+            Self::remove_comment(&env, comment.clone());
+        }
+
+        if let Some(mut post) = Self::live_post(&env, comment.post_id) {
+            post.comments = post.comments.saturating_sub(1);
+            Self::write_post(&env, &post);
+        }
+    }
+
+    pub fn add_moderator(env: Env, moderator: Address) {
+        Self::require_admin(&env);
+        let mut moderators = Self::get_moderators(env.clone());
+        if !moderators.contains(&moderator) {
+            moderators.push_back(moderator);
+            env.storage().instance().set(&DataKey::Moderators, &moderators);
+        }
+    }
+
+    pub fn remove_moderator(env: Env, moderator: Address) {
+        Self::require_admin(&env);
+        let mut moderators = Self::get_moderators(env.clone());
+        if let Some(index) = moderators.first_index_of(&moderator) {
+            moderators.remove(index);
+            env.storage().instance().set(&DataKey::Moderators, &moderators);
+        }
+    }
+
+    pub fn get_moderators(env: Env) -> Vec<Address> {
+        env.storage().instance().get(&DataKey::Moderators).unwrap_or(vec![&env])
+    }
+
+    // Total report weight at which content is hidden
+    pub fn set_report_threshold(env: Env, threshold: u32) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::ReportThreshold, &threshold);
+    }
+
+    pub fn get_report_threshold(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::ReportThreshold).unwrap_or(DEFAULT_REPORT_THRESHOLD)
+    }
+
+    // Give trusted community members' reports more weight. Everyone else counts as 1.
+    pub fn set_reporter_weight(env: Env, reporter: Address, weight: u32) {
+        Self::require_admin(&env);
+        env.storage().persistent().set(&DataKey::ReporterWeight(reporter), &weight);
+    }
+
+    pub fn get_reporter_weight(env: Env, reporter: Address) -> u32 {
+        env.storage().persistent().get(&DataKey::ReporterWeight(reporter)).unwrap_or(1)
+    }
+
+    // Report a post or comment, once per address. Content is hidden once the weighted
+    // reports reach the threshold, unless a moderator has already restored it.
+    pub fn report(env: Env, target: Target, reporter: Address, reason: Symbol) -> ModerationState {
+        Self::require_not_paused(&env);
+        reporter.require_auth();
+        Self::require_target(&env, &target, &Some(reporter.clone()));
+        let key = DataKey::Report(target.clone(), reporter.clone());
+        if env.storage().persistent().has(&key) {
+            panic_with_error!(&env, PostError::AlreadyReported);
+        }
+
+        let weight = Self::get_reporter_weight(env.clone(), reporter);
+        env.storage().persistent().set(&key, &Report { reason, weight, timestamp: env.ledger().timestamp() });
+        let mut state = Self::get_moderation(env.clone(), target.clone());
+        state.reports += 1;
+        state.weight += weight;
+        if state.status == ModerationStatus::Visible && state.weight >= Self::get_report_threshold(env.clone()) {
+            state.status = ModerationStatus::Hidden;
+            Self::set_hidden(&env, &target, true);
+        }
+        env.storage().persistent().set(&DataKey::Moderation(target), &state);
+        state
+    }
+
+    pub fn get_moderation(env: Env, target: Target) -> ModerationState {
+        env.storage().persistent().get(&DataKey::Moderation(target)).unwrap_or(ModerationState {
+            reports: 0,
+            weight: 0,
+            status: ModerationStatus::Visible,
+        })
+    }
+
+    // Ask a moderator to restore hidden content. Anonymous posts require the salt behind their author commitment.
+    pub fn appeal(env: Env, target: Target, author: Address, salt: Option<BytesN<32>>) {
+        Self::require_not_paused(&env);
+        match &target {
+            Target::Post(id) => Self::require_post_author(&env, &Self::load_post(&env, *id), &author, &salt),
+            Target::Comment(id) => Self::require_comment_author(&env, &Self::load_comment(&env, *id), &author, &salt),
+        }
+        let mut state = Self::get_moderation(env.clone(), target.clone());
+        if state.status != ModerationStatus::Hidden {
+            panic_with_error!(&env, PostError::NotAppealable);
+        }
+        state.status = ModerationStatus::Appealed;
+        env.storage().persistent().set(&DataKey::Moderation(target.clone()), &state);
+
+        let mut appeals = Self::get_appeals(env.clone());
+        appeals.push_back(target);
+        env.storage().instance().set(&DataKey::Appeals, &appeals);
+    }
+
+    // Appeals waiting for a moderator, oldest first
+    pub fn get_appeals(env: Env) -> Vec<Target> {
+        env.storage().instance().get(&DataKey::Appeals).unwrap_or(vec![&env])
+    }
+
+    // Settle an appeal: restore the content, or uphold the hide for good
+    pub fn resolve_appeal(env: Env, moderator: Address, target: Target, restore: bool) -> ModerationState {
+        Self::require_moderator(&env, &moderator);
+        let state = Self::get_moderation(env.clone(), target.clone());
+        if state.status != ModerationStatus::Appealed {
+            panic_with_error!(&env, PostError::NotAppealable);
+        }
+        let mut appeals = Self::get_appeals(env.clone());
+        if let Some(index) = appeals.first_index_of(&target) {
+            appeals.remove(index);
+            env.storage().instance().set(&DataKey::Appeals, &appeals);
+        }
+        Self::settle(&env, target, state, restore)
+    }
+
+    // Restore hidden content without waiting for an appeal
+    pub fn restore(env: Env, moderator: Address, target: Target) -> ModerationState {
+        Self::require_moderator(&env, &moderator);
+        let state = Self::get_moderation(env.clone(), target.clone());
+        if state.status != ModerationStatus::Hidden && state.status != ModerationStatus::Upheld {
+            panic_with_error!(&env, PostError::NotAppealable);
+        }
+        Self::settle(&env, target, state, true)
+    }
+
+    // Let ModerationContract juries decide visibility through apply_moderation
+    pub fn set_moderation_contract(env: Env, moderation_contract: Address) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::ModerationContract, &moderation_contract);
+    }
+
+    // Apply a jury verdict. Overrides earlier moderator decisions and settles any pending appeal.
+    pub fn apply_moderation(env: Env, target: Target, hidden: bool) -> ModerationState {
+        let moderation: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::ModerationContract)
+            .unwrap_or_else(|| panic_with_error!(&env, PostError::NotModerator));
+        moderation.require_auth();
+        // Content deleted or expired while the case was open has nothing left to hide
+        if !Self::target_exists(&env, &target) {
+            return Self::get_moderation(env, target);
+        }
+
+        let mut appeals = Self::get_appeals(env.clone());
+        if let Some(index) = appeals.first_index_of(&target) {
+            appeals.remove(index);
+            env.storage().instance().set(&DataKey::Appeals, &appeals);
+        }
+        let state = Self::get_moderation(env.clone(), target.clone());
+        Self::settle(&env, target, state, !hidden)
+    }
+
+    // Delete a post. Anonymous posts require the salt behind their author commitment.
+    // Posts that were reposted leave a tombstone behind so the reposts still resolve.
+    pub fn delete_post(env: Env, id: u64, author: Address, salt: Option<BytesN<32>>) {
+        Self::require_not_paused(&env);
+        let post = Self::load_post(&env, id);
+        Self::require_post_author(&env, &post, &author, &salt);
+        // The escrow can no longer be claimed once the post is gone
+        Self::release_escrow(&env, id, &author);
+        Self::unindex_expiry(&env, &post);
+        Self::remove_post(&env, &post);
+    }
+
+    // Make a post disappear at `expires_at`, at most MAX_POST_LIFETIME from now. Anonymous posts
+    // require the salt behind their author commitment. Tips still in escrow when the post is purged
+    // go back to the tippers.
+    pub fn set_post_expiry(env: Env, id: u64, author: Address, salt: Option<BytesN<32>>, expires_at: u64) -> Post {
+        Self::require_not_paused(&env);
+        let mut post = Self::load_post(&env, id);
+        Self::require_post_author(&env, &post, &author, &salt);
+        let now = env.ledger().timestamp();
+        if expires_at <= now || expires_at > now + MAX_POST_LIFETIME {
+            panic_with_error!(&env, PostError::InvalidExpiry);
+        }
+
+        // Moves the post to temporary storage, so it stops costing rent once expired
+        env.storage().persistent().remove(&DataKey::Post(id));
+        Self::unindex_expiry(&env, &post);
+        post.expires_at = Some(expires_at);
+        Self::write_post(&env, &post);
+        Self::index_expiry(&env, &post);
+        post
+    }
+
+    // Remove expired posts and everything stored for them, oldest expiry first. Anyone may call
+    // this; each call reads or purges at most `limit` (up to MAX_PURGE) expiry buckets and posts.
+    // Returns how many posts were purged.
+    pub fn purge_expired(env: Env, limit: u32) -> u32 {
+        Self::require_not_paused(&env);
+        let now = env.ledger().timestamp();
+        let Some(mut bucket) = env.storage().instance().get::<_, u64>(&DataKey::PurgeFrom) else {
+            return 0;
+        };
+        let mut budget = limit.min(MAX_PURGE);
+        let mut purged = 0;
+        while bucket <= now / EXPIRY_BUCKET && budget > 0 {
+            budget -= 1;
+            let key = DataKey::Expiring(bucket);
+            let mut expiring: Map<u64, ExpiringPost> = env.storage().persistent().get(&key).unwrap_or(Map::new(&env));
+            let mut done = true;
+            for (id, entry) in expiring.clone().iter() {
+                if entry.expires_at > now || budget == 0 {
+                    done = false;
+                    continue;
+                }
+                budget -= 1;
+                expiring.remove(id);
+                Self::refund_escrow(&env, id);
+                match Self::read_post(&env, id).filter(|post| !post.deleted) {
+                    Some(post) => Self::remove_post(&env, &post),
+                    None => {
+                        Self::clear_post_data(&env, id, &entry.author, &entry.tags, entry.timestamp, entry.repost_of)
+                    }
+                }
+                purged += 1;
+            }
+            if expiring.is_empty() {
+                env.storage().persistent().remove(&key);
+            } else {
+                env.storage().persistent().set(&key, &expiring);
+            }
+            if !done {
+                break;
+            }
+            bucket += 1;
+        }
+        env.storage().instance().set(&DataKey::PurgeFrom, &bucket);
+        purged
+    }
+
+    // Replace a post's content within the edit window. Earlier versions stay readable via get_post_revisions.
+    // Anonymous posts require the salt behind their author commitment. Non-public posts hold only ciphertext
+    // and cannot be edited.
+    pub fn edit_post(env: Env, id: u64, author: Address, salt: Option<BytesN<32>>, content: String) -> Post {
+        Self::require_not_paused(&env);
+        let mut post = Self::load_post(&env, id);
+        Self::require_post_author(&env, &post, &author, &salt);
+        if post.hidden {
+            panic_with_error!(&env, PostError::PostNotFound);
+        }
+        if post.visibility != Visibility::Public {
+            panic_with_error!(&env, PostError::InvalidVisibility);
+        }
+        let now = env.ledger().timestamp();
+        if now > post.timestamp.saturating_add(Self::get_edit_window(env.clone())) {
+            panic_with_error!(&env, PostError::EditWindowClosed);
+        }
+
+        let mut revisions = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Revisions(id))
+            .unwrap_or_else(|| vec![&env, Revision { content: post.content.clone(), timestamp: post.timestamp }]);
+        if revisions.len() >= MAX_REVISIONS {
+            panic_with_error!(&env, PostError::TooManyRevisions);
+        }
+        revisions.push_back(Revision { content: content.clone(), timestamp: now });
+        env.storage().persistent().set(&DataKey::Revisions(id), &revisions);
+
+        post.content = content;
+        post.edited = true;
+        Self::write_post(&env, &post);
+        post
+    }
+
+    // Send a SEP-41 token tip to a post's author. Tips on anonymous posts are held in escrow
+    // until the author claims them with `claim_tips`.
+    pub fn tip_post(env: Env, post_id: u64, from: Address, token: Address, amount: i128) -> Map<Address, i128> {
+        Self::require_not_paused(&env);
+        from.require_auth();
+        if amount <= 0 {
+            panic_with_error!(&env, PostError::InvalidAmount);
+        }
+        let post = Self::viewable_post(&env, post_id, &Some(from.clone()))
+            .unwrap_or_else(|| panic_with_error!(&env, PostError::PostNotFound));
+
+        let client = token::Client::new(&env, &token);
+        match post.author {
+            Some(author) => client.transfer(&from, &author, &amount),
+            None => {
+                let key = DataKey::EscrowTip(post_id, from.clone(), token.clone());
+                let tipped = env.storage().persistent().get::<_, i128>(&key);
+                let tippers = List::EscrowTippers(post_id);
+                if tipped.is_none() && Self::list_len(&env, &tippers) >= MAX_ESCROW_TIPPERS {
+                    panic_with_error!(&env, PostError::TooManyTippers);
+                }
+                client.transfer(&from, &env.current_contract_address(), &amount);
+                Self::add_tip(&env, DataKey::Escrow(post_id), &token, amount);
+                if tipped.is_none() {
+                    Self::list_push(&env, &tippers, (from, token.clone()));
+                }
+                env.storage().persistent().set(&key, &(tipped.unwrap_or(0) + amount));
+            }
+        }
+        env.events().publish((symbol_short!("tip"), post_id), (token.clone(), amount));
+        Self::add_tip(&env, DataKey::Tips(post_id), &token, amount)
+    }
+
+    // Total tipped to a post, per token
+    pub fn get_post_tips(env: Env, post_id: u64) -> Map<Address, i128> {
+        env.storage().persistent().get(&DataKey::Tips(post_id)).unwrap_or(Map::new(&env))
+    }
+
+    // Tips waiting for the author of an anonymous post, per token
+    pub fn get_escrowed_tips(env: Env, post_id: u64) -> Map<Address, i128> {
+        env.storage().persistent().get(&DataKey::Escrow(post_id)).unwrap_or(Map::new(&env))
+    }
+
+    // Pay out escrowed tips by revealing the salt behind the author commitment. Like `claim_post`,
+    // this links the post to `author` on the ledger.
+    pub fn claim_tips(env: Env, post_id: u64, author: Address, salt: BytesN<32>) -> Map<Address, i128> {
+        Self::require_not_paused(&env);
+        author.require_auth();
+        let post = Self::load_post(&env, post_id);
+        if !Self::opens_commitment(&env, &post.author_commitment, &author, &Some(salt)) {
+            panic_with_error!(&env, PostError::NotAuthor);
+        }
+        Self::release_escrow(&env, post_id, &author)
+    }
+
+    // Every version of a post, oldest first. The original content is the first entry.
+    // Empty once the post is deleted, hidden or expired, or when the viewer is out of its scope.
+    pub fn get_post_revisions(env: Env, id: u64, viewer: Option<Address>) -> Vec<Revision> {
+        let Some(post) = Self::viewable_post(&env, id, &viewer) else {
+            return vec![&env];
+        };
+        if let Some(revisions) = env.storage().persistent().get(&DataKey::Revisions(id)) {
+            return revisions;
+        }
+        vec![&env, Revision { content: post.content, timestamp: post.timestamp }]
+    }
+
+    // Save a post to one of the owner's bookmark lists, creating the list if needed
+    pub fn add_bookmark(env: Env, owner: Address, list: Symbol, post_id: u64) {
+        Self::require_not_paused(&env);
+        owner.require_auth();
+        Self::load_post(&env, post_id);
+        let bookmarks = List::Bookmarks(owner.clone(), list.clone());
+        if env.storage().persistent().has(&DataKey::ListSlot(bookmarks.clone(), post_id)) {
+            return;
+        }
+        let mut lists = Self::bookmark_lists(&env, &owner);
+        if !lists.contains(&list) {
+            lists.push_back(list);
+            env.storage().persistent().set(&DataKey::BookmarkLists(owner), &lists);
+        }
+        Self::push_id(&env, bookmarks, post_id);
+    }
+
+    pub fn remove_bookmark(env: Env, owner: Address, list: Symbol, post_id: u64) {
+        Self::require_not_paused(&env);
+        owner.require_auth();
+        Self::remove_id(&env, List::Bookmarks(owner, list), post_id);
+    }
+
+    // Bookmarked posts, oldest first. Deleted and hidden posts are skipped, so a page may hold
+    // fewer than `limit` posts. Only the owner may list their bookmarks.
+    pub fn get_bookmarks(env: Env, owner: Address, list: Symbol, offset: u32, limit: u32) -> Vec<Post> {
+        owner.require_auth();
+        let bookmarks = List::Bookmarks(owner.clone(), list);
+        let ids: Vec<u64> =
+            Self::list_range(&env, &bookmarks, offset, offset.saturating_add(limit.min(MAX_BOOKMARK_PAGE)));
+
+        let viewer = Some(owner);
+        let mut page = vec![&env];
+        for id in ids.iter() {
+            if let Some(post) = Self::viewable_post(&env, id, &viewer) {
+                page.push_back(post);
+            }
+        }
+        page
+    }
+
+    pub fn get_bookmark_lists(env: Env, owner: Address) -> Vec<Symbol> {
+        owner.require_auth();
+        Self::bookmark_lists(&env, &owner)
+    }
+
+    pub fn get_poll(env: Env, post_id: u64) -> Option<Poll> {
+        env.storage().persistent().get(&DataKey::Poll(post_id))
+    }
+
+    // Vote on an open poll. Each address votes once.
+    pub fn vote_poll(env: Env, post_id: u64, voter: Address, option: u32) -> Poll {
+        Self::require_not_paused(&env);
+        voter.require_auth();
+        let poll = Self::open_poll(&env, post_id, &voter);
+        if poll.sealed {
+            panic_with_error!(&env, PostError::InvalidPoll);
+        }
+        Self::count_vote(&env, post_id, &poll, option);
+        Self::store_ballot(&env, post_id, &voter, poll, Ballot::Cast(option))
+    }
+
+    // Commit to a vote on an open sealed poll. See `vote_commitment` for how the commitment is built.
+    pub fn commit_poll_vote(env: Env, post_id: u64, voter: Address, commitment: BytesN<32>) -> Poll {
+        Self::require_not_paused(&env);
+        voter.require_auth();
+        let poll = Self::open_poll(&env, post_id, &voter);
+        if !poll.sealed {
+            panic_with_error!(&env, PostError::InvalidPoll);
+        }
+        Self::store_ballot(&env, post_id, &voter, poll, Ballot::Committed(commitment))
+    }
+
+    // Reveal a committed vote once the poll has closed, within POLL_REVEAL_WINDOW
+    pub fn reveal_poll_vote(env: Env, post_id: u64, voter: Address, option: u32, salt: BytesN<32>) {
+        Self::require_not_paused(&env);
+        Self::require_viewer(&env, &Self::load_post(&env, post_id), &voter);
+        let poll = Self::load_poll(&env, post_id);
+        let now = env.ledger().timestamp();
+        if now < poll.closes_at {
+            panic_with_error!(&env, PostError::PollOpen);
+        }
+        if now >= poll.closes_at + POLL_REVEAL_WINDOW {
+            panic_with_error!(&env, PostError::PollClosed);
+        }
+        let key = DataKey::Ballot(post_id, voter.clone());
+        match env.storage().persistent().get(&key) {
+            Some(Ballot::Committed(commitment)) if commitment == vote_commitment(&env, &voter, option, &salt) => {}
+            _ => panic_with_error!(&env, PostError::InvalidReveal),
+        }
+
+        Self::count_vote(&env, post_id, &poll, option);
+        env.storage().persistent().set(&key, &Ballot::Cast(option));
+    }
+
+    // Votes per option, in the order of the poll's options. Sealed polls keep counting
+    // revealed votes until the reveal window ends.
+    pub fn get_poll_results(env: Env, post_id: u64) -> Vec<u32> {
+        let poll = Self::load_poll(&env, post_id);
+        if env.ledger().timestamp() < poll.closes_at {
+            panic_with_error!(&env, PostError::PollOpen);
+        }
+        env.storage().persistent().get(&DataKey::PollTally(post_id)).unwrap_or(vec![&env])
+    }
+
+    fn store_new_post(
+        env: &Env,
+        author: Option<Address>,
+        author_commitment: Option<BytesN<32>>,
+        content: String,
+        tags: Vec<Symbol>,
+        poll: bool,
+        repost_of: Option<u64>,
+    ) -> Post {
+        let id = env.storage().instance().get::<_, u64>(&DataKey::PostCount).unwrap_or(0) + 1;
+        let post = Post {
+            id,
+            content,
+            timestamp: env.ledger().timestamp(),
+            anonymous: author_commitment.is_some(),
+            author,
+            author_commitment: author_commitment.map(Bytes::from),
+            tags: tags.clone(),
+            likes: 0,
+            comments: 0,
+            reactions: Map::new(env),
+            edited: false,
+            hidden: false,
+            poll,
+            repost_of,
+            reposts: 0,
+            deleted: false,
+            expires_at: None,
+            visibility: Visibility::Public,
+            ciphertext: None,
+        };
+        Self::write_post(env, &post);
+        env.storage().instance().set(&DataKey::PostCount, &id);
+        for tag in tags.iter() {
+            Self::push_id(env, List::TagPosts(tag.clone()), id);
+            let count = Self::get_tag_post_count(env.clone(), tag.clone());
+            env.storage().persistent().set(&DataKey::TagCount(tag), &(count + 1));
+        }
+        Self::record_tag_activity(env, &tags, 1, 0);
+        Self::rank(env, DataKey::FeedNewest, FeedEntry { id, score: post.timestamp, created_at: post.timestamp });
+        Self::rank_likes(env, &post);
+        post
+    }
+
+    // Add to the current hourly bucket of every tag
+    fn record_tag_activity(env: &Env, tags: &Vec<Symbol>, posts: u32, engagement: u32) {
+        if tags.is_empty() {
+            return;
+        }
+        let key = DataKey::TagBucket(env.ledger().timestamp() / TAG_BUCKET);
+        let mut bucket: Map<Symbol, TagStats> = env.storage().temporary().get(&key).unwrap_or(Map::new(env));
+        for tag in tags.iter() {
+            let mut stats = bucket.get(tag.clone()).unwrap_or_default();
+            stats.posts += posts;
+            stats.engagement += engagement;
+            bucket.set(tag, stats);
+        }
+        env.storage().temporary().set(&key, &bucket);
+        let ttl = ((MAX_TRENDING_WINDOW + TAG_BUCKET) / LEDGER_SECONDS) as u32;
+        env.storage().temporary().extend_ttl(&key, ttl, ttl);
+    }
+
+    // Re-score a post in the like-based indexes
+    fn rank_likes(env: &Env, post: &Post) {
+        let created_at = post.timestamp;
+        let hot = created_at * HOT_SCALE / HOT_HALF_LIFE + u64::from((post.likes + 1).ilog2()) * HOT_SCALE;
+        let top = DataKey::FeedTop(created_at / TOP_BUCKET);
+        Self::rank(env, top, FeedEntry { id: post.id, score: post.likes.into(), created_at });
+        Self::rank(env, DataKey::FeedHot, FeedEntry { id: post.id, score: hot, created_at });
+    }
+
+    // Insert or move an entry, dropping the lowest-ranked one past FEED_INDEX_CAP
+    fn rank(env: &Env, key: DataKey, entry: FeedEntry) {
+        let mut entries: Vec<FeedEntry> = env.storage().persistent().get(&key).unwrap_or(vec![env]);
+        if let Some(index) = entries.iter().position(|e| e.id == entry.id) {
+            entries.remove(index as u32);
+        }
+        entries.insert(Self::feed_position(&entries, entry.score, entry.id), entry);
+        if entries.len() > FEED_INDEX_CAP {
+            entries.pop_back();
+        }
+        env.storage().persistent().set(&key, &entries);
+    }
+
+    fn unrank(env: &Env, key: DataKey, id: u64) {
+        let mut entries: Vec<FeedEntry> = env.storage().persistent().get(&key).unwrap_or(vec![env]);
+        if let Some(index) = entries.iter().position(|e| e.id == id) {
+            entries.remove(index as u32);
+            env.storage().persistent().set(&key, &entries);
+        }
+    }
+
+    // The best-ranked entry at the heads of the indexes, with the index it came from
+    fn next_feed_entry(indexes: &Vec<Vec<FeedEntry>>, heads: &Vec<u32>) -> Option<(u32, FeedEntry)> {
+        let mut best: Option<(u32, FeedEntry)> = None;
+        for index in 0..indexes.len() {
+            let entries = indexes.get_unchecked(index);
+            let head = heads.get_unchecked(index);
+            if head >= entries.len() {
+                continue;
+            }
+            let entry = entries.get_unchecked(head);
+            if best.as_ref().is_none_or(|(_, b)| (entry.score, entry.id) > (b.score, b.id)) {
+                best = Some((index, entry));
+            }
+        }
+        best
+    }
+
+    // Index of the first entry ranked below (score, id)
+    fn feed_position(entries: &Vec<FeedEntry>, score: u64, id: u64) -> u32 {
+        let (mut low, mut high) = (0, entries.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            let entry = entries.get_unchecked(mid);
+            if (entry.score, entry.id) >= (score, id) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    fn require_post_author(env: &Env, post: &Post, author: &Address, salt: &Option<BytesN<32>>) {
+        author.require_auth();
+        if post.author.as_ref() != Some(author) && !Self::opens_commitment(env, &post.author_commitment, author, salt) {
+            panic_with_error!(env, PostError::NotAuthor);
+        }
+    }
+
+    fn require_comment_author(env: &Env, comment: &Comment, author: &Address, salt: &Option<BytesN<32>>) {
+        author.require_auth();
+        if comment.author.as_ref() != Some(author)
+            && !Self::opens_commitment(env, &comment.author_commitment, author, salt)
+        {
+            panic_with_error!(env, PostError::NotAuthor);
+        }
+    }
+
+    fn opens_commitment(env: &Env, commitment: &Option<Bytes>, author: &Address, salt: &Option<BytesN<32>>) -> bool {
+        match (commitment, salt) {
+            (Some(commitment), Some(salt)) => Bytes::from(author_commitment(env, author, salt)) == *commitment,
+            _ => false,
+        }
+    }
+
+    fn store_repost(env: &Env, post_id: u64, author: &Address, content: String) -> Post {
+        let mut original =
+            Self::visible_post(env, post_id).unwrap_or_else(|| panic_with_error!(env, PostError::PostNotFound));
+        if original.visibility != Visibility::Public {
+            panic_with_error!(env, PostError::InvalidVisibility);
+        }
+        original.reposts += 1;
+        Self::write_post(env, &original);
+
+        let post = Self::store_new_post(env, Some(author.clone()), None, content, vec![env], false, Some(post_id));
+        Self::push_id(env, List::UserPosts(author.clone()), post.id);
+        post
+    }
+
+    fn bookmark_lists(env: &Env, owner: &Address) -> Vec<Symbol> {
+        env.storage().persistent().get(&DataKey::BookmarkLists(owner.clone())).unwrap_or(vec![env])
+    }
+
+    fn load_poll(env: &Env, post_id: u64) -> Poll {
+        Self::get_poll(env.clone(), post_id).unwrap_or_else(|| panic_with_error!(env, PostError::NotAPoll))
+    }
+
+    // The poll if it still accepts votes and `voter` has not voted yet
+    fn open_poll(env: &Env, post_id: u64, voter: &Address) -> Poll {
+        Self::require_viewer(env, &Self::load_post(env, post_id), voter);
+        let poll = Self::load_poll(env, post_id);
+        if env.ledger().timestamp() >= poll.closes_at {
+            panic_with_error!(env, PostError::PollClosed);
+        }
+        if env.storage().persistent().has(&DataKey::Ballot(post_id, voter.clone())) {
+            panic_with_error!(env, PostError::AlreadyVoted);
+        }
+        poll
+    }
+
+    fn store_ballot(env: &Env, post_id: u64, voter: &Address, mut poll: Poll, ballot: Ballot) -> Poll {
+        env.storage().persistent().set(&DataKey::Ballot(post_id, voter.clone()), &ballot);
+        poll.voters += 1;
+        env.storage().persistent().set(&DataKey::Poll(post_id), &poll);
+        poll
+    }
+
+    fn count_vote(env: &Env, post_id: u64, poll: &Poll, option: u32) {
+        if option >= poll.options.len() {
+            panic_with_error!(env, PostError::InvalidPoll);
+        }
+        let key = DataKey::PollTally(post_id);
+        let mut tally: Vec<u32> = env.storage().persistent().get(&key).unwrap_or(vec![env]);
+        tally.set(option, tally.get_unchecked(option) + 1);
+        env.storage().persistent().set(&key, &tally);
+    }
+
+    fn add_tip(env: &Env, key: DataKey, token: &Address, amount: i128) -> Map<Address, i128> {
+        let mut tips: Map<Address, i128> = env.storage().persistent().get(&key).unwrap_or(Map::new(env));
+        tips.set(token.clone(), tips.get(token.clone()).unwrap_or(0) + amount);
+        env.storage().persistent().set(&key, &tips);
+        tips
+    }
+
+    fn release_escrow(env: &Env, post_id: u64, author: &Address) -> Map<Address, i128> {
+        let escrow = Self::get_escrowed_tips(env.clone(), post_id);
+        for (token, amount) in escrow.iter() {
+            token::Client::new(env, &token).transfer(&env.current_contract_address(), author, &amount);
+        }
+        env.storage().persistent().remove(&DataKey::Escrow(post_id));
+        Self::take_escrow_tips(env, post_id);
+        escrow
+    }
+
+    // Give unclaimed tips back to the tippers once nobody can claim them any more
+    fn refund_escrow(env: &Env, post_id: u64) {
+        for (tipper, token, amount) in Self::take_escrow_tips(env, post_id).iter() {
+            token::Client::new(env, &token).transfer(&env.current_contract_address(), &tipper, &amount);
+        }
+        env.storage().persistent().remove(&DataKey::Escrow(post_id));
     }
 
-    pub fn get_post(env: Env, id: u64) -> Option<Post> {
-        env.storage().get(&id)
+    // Drop the records of who paid into a post's escrow, returning (tipper, token, amount) for each
+    fn take_escrow_tips(env: &Env, post_id: u64) -> Vec<(Address, Address, i128)> {
+        let list = List::EscrowTippers(post_id);
+        let tippers: Vec<(Address, Address)> = Self::list_range(env, &list, 0, MAX_ESCROW_TIPPERS);
+        let mut tips = vec![env];
+        for (tipper, token) in tippers.iter() {
+            let key = DataKey::EscrowTip(post_id, tipper.clone(), token.clone());
+            tips.push_back((tipper, token, env.storage().persistent().get(&key).unwrap_or(0)));
+            env.storage().persistent().remove(&key);
+        }
+        Self::list_clear(env, &list);
+        tips
     }
 
-    pub fn get_all_posts(env: Env) -> Vec<Post> {
-        // For simplicity, this is a placeholder. Real implementation would iterate storage.
-        Vec::new(&env)
+    fn default_reactions(env: &Env) -> Vec<Symbol> {
+        vec![
+            env,
+            symbol_short!("like"),
+            symbol_short!("love"),
+            symbol_short!("haha"),
+            symbol_short!("wow"),
+            symbol_short!("sad"),
+            symbol_short!("angry"),
+        ]
+    }
+
+    fn load_reactions(env: &Env, target: &Target) -> Map<Symbol, u32> {
+        match target {
+            Target::Post(id) => Self::load_post(env, *id).reactions,
+            Target::Comment(id) => Self::load_comment(env, *id).reactions,
+        }
+    }
+
+    // Add or remove one reaction on the target's stored counts and return the new counts
+    fn count_reaction(env: &Env, target: &Target, reaction: &Symbol, add: bool) -> Map<Symbol, u32> {
+        let bump = |counts: &mut Map<Symbol, u32>| {
+            let count = counts.get(reaction.clone()).unwrap_or(0);
+            let count = if add { count + 1 } else { count.saturating_sub(1) };
+            if count == 0 {
+                counts.remove(reaction.clone());
+            } else {
+                counts.set(reaction.clone(), count);
+            }
+            count
+        };
+        match target {
+            Target::Post(id) => {
+                let mut post = Self::load_post(env, *id);
+                let count = bump(&mut post.reactions);
+                let is_like = *reaction == symbol_short!("like");
+                if is_like {
+                    post.likes = count;
+                }
+                Self::write_post(env, &post);
+                if is_like {
+                    Self::rank_likes(env, &post);
+                }
+                post.reactions
+            }
+            Target::Comment(id) => {
+                let mut comment = Self::load_comment(env, *id);
+                bump(&mut comment.reactions);
+                env.storage().persistent().set(&DataKey::Comment(*id), &comment);
+                comment.reactions
+            }
+        }
+    }
+
+    fn require_reaction_type(env: &Env, reaction: &Symbol) {
+        if !Self::get_reaction_types(env.clone()).contains(reaction) {
+            panic_with_error!(env, PostError::InvalidReaction);
+        }
+    }
+
+    // Drop the reaction stored under `key` and return the target's new counts
+    fn remove_reaction(env: &Env, target: &Target, key: DataKey, reactor: ReactorKey) -> Map<Symbol, u32> {
+        let Some(previous) = env.storage().persistent().get::<_, UserReaction>(&key) else {
+            return Self::load_reactions(env, target);
+        };
+        env.storage().persistent().remove(&key);
+        let slot_key = DataKey::ReactorSlot(target.clone(), reactor);
+        if let Some(slot) = env.storage().persistent().get::<_, u32>(&slot_key) {
+            env.storage().persistent().remove(&slot_key);
+            Self::list_remove::<ReactorKey>(env, &List::Reactors(target.clone()), slot);
+        }
+        Self::count_reaction(env, target, &previous.reaction, false)
+    }
+
+    fn push_reactor(env: &Env, target: &Target, reactor: ReactorKey) {
+        let slot = Self::list_push(env, &List::Reactors(target.clone()), reactor.clone());
+        env.storage().persistent().set(&DataKey::ReactorSlot(target.clone(), reactor), &slot);
+    }
+
+    // Count a user's first reaction on a post toward tag engagement, and their first like toward
+    // LikeReceived, so switching or repeating reactions cannot inflate either. Only public reactions
+    // are recorded, so the map never holds the address behind an anonymous one.
+    fn record_engagement(env: &Env, post_id: u64, user: Address, reaction: &Symbol) {
+        let key = DataKey::Engaged(post_id);
+        let mut engaged: Map<Address, bool> = env.storage().persistent().get(&key).unwrap_or(Map::new(env));
+        let liked = engaged.get(user.clone());
+        if liked.is_none() && engaged.len() >= ENGAGED_CAP {
+            return;
+        }
+        let is_like = *reaction == symbol_short!("like");
+        if liked.is_none() {
+            Self::record_tag_activity(env, &Self::load_post(env, post_id).tags, 0, 1);
+        }
+        if is_like && liked != Some(true) {
+            Self::report_like(env, post_id);
+        }
+        if liked != Some(true) && liked != Some(is_like) {
+            engaged.set(user, is_like);
+            env.storage().persistent().set(&key, &engaged);
+        }
+    }
+
+    // Analytics are best effort: a failing or misconfigured analytics contract never blocks a like
+    fn report_like(env: &Env, post_id: u64) {
+        let author = Self::load_post(env, post_id).author;
+        if let (Some(author), Some(analytics)) =
+            (author, env.storage().instance().get::<_, Address>(&DataKey::AnalyticsContract))
+        {
+            let _ = AnalyticsClient::new(env, &analytics).try_record_event(
+                &env.current_contract_address(),
+                &author,
+                &AnalyticsEvent::LikeReceived,
+            );
+        }
+    }
+
+    // The reply lists to walk when resuming at `cursor`: its own list from the cursor on, then the
+    // lists of each of its ancestors up to the requested parent, from just after the ancestor
+    fn resume_thread(env: &Env, post_id: u64, parent_comment_id: Option<u64>, cursor: u64) -> Vec<(Option<u64>, u32)> {
+        let mut open = vec![env];
+        let (mut id, mut skip) = (cursor, 0);
+        loop {
+            let comment = Self::load_comment(env, id);
+            if comment.post_id != post_id {
+                panic_with_error!(env, PostError::CommentNotFound);
+            }
+            let slot: u32 = env
+                .storage()
+                .persistent()
+                .get(&DataKey::ListSlot(Self::thread_list(post_id, comment.parent_comment_id), id))
+                .unwrap_or_else(|| panic_with_error!(env, PostError::CommentNotFound));
+            open.push_front((comment.parent_comment_id, slot + skip));
+            if comment.parent_comment_id == parent_comment_id {
+                return open;
+            }
+            let Some(parent_id) = comment.parent_comment_id else {
+                panic_with_error!(env, PostError::CommentNotFound);
+            };
+            (id, skip) = (parent_id, 1);
+        }
+    }
+
+    fn thread_list(post_id: u64, parent_comment_id: Option<u64>) -> List {
+        match parent_comment_id {
+            Some(parent_id) => List::CommentReplies(parent_id),
+            None => List::PostComments(post_id),
+        }
+    }
+
+    fn load_comment(env: &Env, id: u64) -> Comment {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Comment(id))
+            .unwrap_or_else(|| panic_with_error!(env, PostError::CommentNotFound))
+    }
+
+    fn load_post(env: &Env, id: u64) -> Post {
+        Self::live_post(env, id)
+            .filter(|post| !post.deleted)
+            .unwrap_or_else(|| panic_with_error!(env, PostError::PostNotFound))
+    }
+
+    fn visible_post(env: &Env, id: u64) -> Option<Post> {
+        Self::live_post(env, id).filter(|post| !post.hidden && !post.deleted)
+    }
+
+    fn viewable_post(env: &Env, id: u64, viewer: &Option<Address>) -> Option<Post> {
+        Self::visible_post(env, id).filter(|post| Self::can_view(env, post, viewer))
+    }
+
+    // Whether can_view has to ask the follow or match contract
+    fn needs_scope_lookup(post: &Post, viewer: &Option<Address>) -> bool {
+        post.visibility != Visibility::Public
+            && matches!((viewer, &post.author), (Some(viewer), Some(author)) if viewer != author)
+    }
+
+    // Whether the viewer is in the post's scope, asking the follow or match contract if needed
+    fn can_view(env: &Env, post: &Post, viewer: &Option<Address>) -> bool {
+        if post.visibility == Visibility::Public {
+            return true;
+        }
+        let (Some(viewer), Some(author)) = (viewer, &post.author) else {
+            return false;
+        };
+        if viewer == author {
+            return true;
+        }
+        match post.visibility {
+            Visibility::Public => true,
+            Visibility::Followers => env
+                .storage()
+                .instance()
+                .get::<_, Address>(&DataKey::FollowContract)
+                .is_some_and(|contract| FollowClient::new(env, &contract).is_following(viewer, author)),
+            Visibility::Matches => env
+                .storage()
+                .instance()
+                .get::<_, Address>(&DataKey::MatchContract)
+                .is_some_and(|contract| MatchClient::new(env, &contract).is_matched(viewer, author)),
+        }
+    }
+
+    // The post unless it has expired
+    fn live_post(env: &Env, id: u64) -> Option<Post> {
+        Self::read_post(env, id).filter(|post| !Self::is_expired(env, post))
+    }
+
+    fn read_post(env: &Env, id: u64) -> Option<Post> {
+        let key = DataKey::Post(id);
+        env.storage().persistent().get(&key).or_else(|| env.storage().temporary().get(&key))
+    }
+
+    // Expiring posts live in temporary storage until shortly after they expire
+    fn write_post(env: &Env, post: &Post) {
+        let key = DataKey::Post(post.id);
+        match post.expires_at {
+            Some(expires_at) => {
+                env.storage().temporary().set(&key, post);
+                let remaining = expires_at.saturating_sub(env.ledger().timestamp()) + PURGE_GRACE;
+                let ttl = (remaining / LEDGER_SECONDS) as u32;
+                env.storage().temporary().extend_ttl(&key, ttl, ttl);
+            }
+            None => env.storage().persistent().set(&key, post),
+        }
+    }
+
+    // Remove a deleted or expired post and everything stored for it. Reposted posts leave a
+    // tombstone behind so their reposts still resolve.
+    fn remove_post(env: &Env, post: &Post) {
+        let key = DataKey::Post(post.id);
+        env.storage().temporary().remove(&key);
+        env.storage().persistent().remove(&key);
+        if post.reposts > 0 {
+            let tombstone = Post {
+                content: String::from_str(env, ""),
+                author: None,
+                author_commitment: None,
+                tags: vec![env],
+                deleted: true,
+                expires_at: None,
+                ..post.clone()
+            };
+            Self::write_post(env, &tombstone);
+        }
+        Self::clear_post_data(env, post.id, &post.author, &post.tags, post.timestamp, post.repost_of);
+    }
+
+    // Drop a post's revisions, poll, tips, reactions, comments and index entries
+    fn clear_post_data(
+        env: &Env,
+        id: u64,
+        author: &Option<Address>,
+        tags: &Vec<Symbol>,
+        timestamp: u64,
+        repost_of: Option<u64>,
+    ) {
+        let storage = env.storage().persistent();
+        for key in [
+            DataKey::Revisions(id),
+            DataKey::Poll(id),
+            DataKey::PollTally(id),
+            DataKey::Tips(id),
+            DataKey::Engaged(id),
+            DataKey::Moderation(Target::Post(id)),
+        ] {
+            storage.remove(&key);
+        }
+        Self::clear_reactions(env, &Target::Post(id));
+
+        // Comments, breadth-first through the reply lists
+        let mut pending = Self::clear_ids(env, List::PostComments(id));
+        while let Some(comment_id) = pending.pop_back() {
+            pending.append(&Self::clear_ids(env, List::CommentReplies(comment_id)));
+            for key in [DataKey::Comment(comment_id), DataKey::Moderation(Target::Comment(comment_id))] {
+                storage.remove(&key);
+            }
+            Self::clear_reactions(env, &Target::Comment(comment_id));
+        }
+
+        if let Some(author) = author {
+            Self::remove_id(env, List::UserPosts(author.clone()), id);
+        }
+        for tag in tags.iter() {
+            Self::remove_id(env, List::TagPosts(tag.clone()), id);
+            let count = Self::get_tag_post_count(env.clone(), tag.clone());
+            storage.set(&DataKey::TagCount(tag), &count.saturating_sub(1));
+        }
+        for key in [DataKey::FeedNewest, DataKey::FeedTop(timestamp / TOP_BUCKET), DataKey::FeedHot] {
+            Self::unrank(env, key, id);
+        }
+        if let Some(original_id) = repost_of {
+            if let Some(mut original) = Self::live_post(env, original_id) {
+                original.reposts = original.reposts.saturating_sub(1);
+                Self::write_post(env, &original);
+            }
+            if let Some(author) = author {
+                let key = DataKey::Reposted(original_id, author.clone());
+                if storage.get(&key) == Some(id) {
+                    storage.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn clear_reactions(env: &Env, target: &Target) {
+        let storage = env.storage().persistent();
+        let list = List::Reactors(target.clone());
+        let reactors: Vec<ReactorKey> = Self::list_range(env, &list, 0, u32::MAX);
+        for reactor in reactors.iter() {
+            match reactor.clone() {
+                ReactorKey::User(user) => storage.remove(&DataKey::Reaction(target.clone(), user)),
+                ReactorKey::Anonymous(commitment) => storage.remove(&DataKey::AnonReaction(target.clone(), commitment)),
+            }
+            storage.remove(&DataKey::ReactorSlot(target.clone(), reactor));
+        }
+        Self::list_clear(env, &list);
+    }
+
+    // Record an expiring post in the bucket of its expiry day
+    fn index_expiry(env: &Env, post: &Post) {
+        let Some(expires_at) = post.expires_at else {
+            return;
+        };
+        let bucket = expires_at / EXPIRY_BUCKET;
+        let key = DataKey::Expiring(bucket);
+        let mut expiring: Map<u64, ExpiringPost> = env.storage().persistent().get(&key).unwrap_or(Map::new(env));
+        let entry = ExpiringPost {
+            expires_at,
+            author: post.author.clone(),
+            tags: post.tags.clone(),
+            timestamp: post.timestamp,
+            repost_of: post.repost_of,
+        };
+        expiring.set(post.id, entry);
+        env.storage().persistent().set(&key, &expiring);
+        let purge_from = env.storage().instance().get::<_, u64>(&DataKey::PurgeFrom);
+        if purge_from.is_none_or(|purge_from| bucket < purge_from) {
+            env.storage().instance().set(&DataKey::PurgeFrom, &bucket);
+        }
+    }
+
+    fn unindex_expiry(env: &Env, post: &Post) {
+        let Some(expires_at) = post.expires_at else {
+            return;
+        };
+        let key = DataKey::Expiring(expires_at / EXPIRY_BUCKET);
+        let mut expiring: Map<u64, ExpiringPost> = env.storage().persistent().get(&key).unwrap_or(Map::new(env));
+        if expiring.remove(post.id).is_some() {
+            env.storage().persistent().set(&key, &expiring);
+        }
+    }
+
+    fn is_expired(env: &Env, post: &Post) -> bool {
+        post.expires_at.is_some_and(|expires_at| env.ledger().timestamp() >= expires_at)
+    }
+
+    // A page of posts referenced by an id list, with `offset` counting slots. Removed, deleted and
+    // hidden posts are skipped, so a page may hold fewer than `limit` posts.
+    fn load_posts(env: &Env, list: List, viewer: &Option<Address>, offset: u32, limit: u32) -> Vec<Post> {
+        let ids: Vec<u64> = Self::list_range(env, &list, offset, offset.saturating_add(limit.min(MAX_POST_PAGE)));
+        let mut posts = vec![env];
+        for id in ids.iter() {
+            if let Some(post) = Self::viewable_post(env, id, viewer) {
+                posts.push_back(post);
+            }
+        }
+        posts
+    }
+
+    // Remove a comment without replies, then every tombstoned ancestor left without replies
+    fn remove_comment(env: &Env, mut comment: Comment) {
+        loop {
+            env.storage().persistent().remove(&DataKey::Comment(comment.id));
+            Self::clear_ids(env, List::CommentReplies(comment.id));
+            let Some(parent_id) = comment.parent_comment_id else {
+                return Self::remove_id(env, List::PostComments(comment.post_id), comment.id);
+            };
+            Self::remove_id(env, List::CommentReplies(parent_id), comment.id);
+            let mut parent = Self::load_comment(env, parent_id);
+            parent.replies -= 1;
+            if !parent.deleted || parent.replies > 0 {
+                return env.storage().persistent().set(&DataKey::Comment(parent_id), &parent);
+            }
+            comment = parent;
+        }
+    }
+
+    fn push_id(env: &Env, list: List, id: u64) {
+        let slot = Self::list_push(env, &list, id);
+        env.storage().persistent().set(&DataKey::ListSlot(list, id), &slot);
+    }
+
+    fn remove_id(env: &Env, list: List, id: u64) {
+        let key = DataKey::ListSlot(list.clone(), id);
+        if let Some(slot) = env.storage().persistent().get::<_, u32>(&key) {
+            env.storage().persistent().remove(&key);
+            Self::list_remove::<u64>(env, &list, slot);
+        }
+    }
+
+    // Remove an id list with the slots of its ids, returning the ids it held
+    fn clear_ids(env: &Env, list: List) -> Vec<u64> {
+        let ids: Vec<u64> = Self::list_range(env, &list, 0, u32::MAX);
+        for id in ids.iter() {
+            env.storage().persistent().remove(&DataKey::ListSlot(list.clone(), id));
+        }
+        Self::list_clear(env, &list);
+        ids
+    }
+
+    fn list_len(env: &Env, list: &List) -> u32 {
+        env.storage().persistent().get(&DataKey::ListLen(list.clone())).unwrap_or(0)
+    }
+
+    fn list_page<V: IntoVal<Env, Val> + TryFromVal<Env, Val>>(env: &Env, list: &List, page: u32) -> Map<u32, V> {
+        env.storage().persistent().get(&DataKey::ListPage(list.clone(), page)).unwrap_or(Map::new(env))
+    }
+
+    // Append an item and return its slot
+    fn list_push<V: IntoVal<Env, Val> + TryFromVal<Env, Val>>(env: &Env, list: &List, item: V) -> u32 {
+        let slot = Self::list_len(env, list);
+        let mut page = Self::list_page(env, list, slot / LIST_PAGE);
+        page.set(slot % LIST_PAGE, item);
+        env.storage().persistent().set(&DataKey::ListPage(list.clone(), slot / LIST_PAGE), &page);
+        env.storage().persistent().set(&DataKey::ListLen(list.clone()), &(slot + 1));
+        slot
+    }
+
+    fn list_remove<V: IntoVal<Env, Val> + TryFromVal<Env, Val>>(env: &Env, list: &List, slot: u32) {
+        let key = DataKey::ListPage(list.clone(), slot / LIST_PAGE);
+        let mut page: Map<u32, V> = Self::list_page(env, list, slot / LIST_PAGE);
+        page.remove(slot % LIST_PAGE);
+        if page.is_empty() {
+            env.storage().persistent().remove(&key);
+        } else {
+            env.storage().persistent().set(&key, &page);
+        }
+    }
+
+    // Items in slots from..to, in slot order
+    fn list_range<V: IntoVal<Env, Val> + TryFromVal<Env, Val> + Clone>(
+        env: &Env,
+        list: &List,
+        from: u32,
+        to: u32,
+    ) -> Vec<V> {
+        let to = to.min(Self::list_len(env, list));
+        let mut items = vec![env];
+        let mut slot = from;
+        while slot < to {
+            let page: Map<u32, V> = Self::list_page(env, list, slot / LIST_PAGE);
+            let base = slot / LIST_PAGE * LIST_PAGE;
+            let end = (base + LIST_PAGE).min(to);
+            for (i, item) in page.iter() {
+                if (slot..end).contains(&(base + i)) {
+                    items.push_back(item);
+                }
+            }
+            slot = end;
+        }
+        items
+    }
+
+    // The first item at or after `slot`, with its slot
+    fn list_next<V: IntoVal<Env, Val> + TryFromVal<Env, Val> + Clone>(
+        env: &Env,
+        list: &List,
+        slot: u32,
+    ) -> Option<(u32, V)> {
+        let len = Self::list_len(env, list);
+        let mut slot = slot;
+        while slot < len {
+            let page: Map<u32, V> = Self::list_page(env, list, slot / LIST_PAGE);
+            let base = slot / LIST_PAGE * LIST_PAGE;
+            if let Some((i, item)) = page.iter().find(|(i, _)| base + i >= slot) {
+                return Some((base + i, item));
+            }
+            slot = base + LIST_PAGE;
+        }
+        None
+    }
+
+    fn list_clear(env: &Env, list: &List) {
+        for page in 0..Self::list_len(env, list).div_ceil(LIST_PAGE) {
+            env.storage().persistent().remove(&DataKey::ListPage(list.clone(), page));
+        }
+        env.storage().persistent().remove(&DataKey::ListLen(list.clone()));
+    }
+
+    fn settle(env: &Env, target: Target, mut state: ModerationState, restore: bool) -> ModerationState {
+        state.status = if restore { ModerationStatus::Restored } else { ModerationStatus::Upheld };
+        Self::set_hidden(env, &target, !restore);
+        env.storage().persistent().set(&DataKey::Moderation(target.clone()), &state);
+        env.events().publish((symbol_short!("moderated"), target), state.status);
+        state
+    }
+
+    fn set_hidden(env: &Env, target: &Target, hidden: bool) {
+        match target {
+            Target::Post(id) => {
+                let mut post = Self::load_post(env, *id);
+                post.hidden = hidden;
+                Self::write_post(env, &post);
+            }
+            Target::Comment(id) => {
+                let mut comment = Self::load_comment(env, *id);
+                comment.hidden = hidden;
+                env.storage().persistent().set(&DataKey::Comment(*id), &comment);
+            }
+        }
+    }
+
+    // Fail unless the target is live, not hidden by moderation, and in the viewer's scope
+    fn require_target(env: &Env, target: &Target, viewer: &Option<Address>) {
+        let post_id = match target {
+            Target::Post(id) => *id,
+            Target::Comment(id) => {
+                let comment = Self::load_comment(env, *id);
+                if comment.deleted || comment.hidden {
+                    panic_with_error!(env, PostError::CommentNotFound);
+                }
+                comment.post_id
+            }
+        };
+        let post = Self::visible_post(env, post_id).unwrap_or_else(|| panic_with_error!(env, PostError::PostNotFound));
+        if !Self::can_view(env, &post, viewer) {
+            panic_with_error!(env, PostError::PostNotFound);
+        }
+    }
+
+    // Out-of-scope posts fail as if they did not exist
+    fn store_comment(
+        env: &Env,
+        mut post: Post,
+        author: Option<Address>,
+        author_commitment: Option<BytesN<32>>,
+        content: String,
+        parent_comment_id: Option<u64>,
+    ) -> Comment {
+        let post_id = post.id;
+        let id = env.storage().instance().get::<_, u64>(&DataKey::CommentCount).unwrap_or(0) + 1;
+        let depth = match parent_comment_id {
+            Some(parent_id) => {
+                let mut parent = Self::load_comment(env, parent_id);
+                if parent.post_id != post_id || parent.hidden {
+                    panic_with_error!(env, PostError::CommentNotFound);
+                }
+                parent.replies += 1;
+                env.storage().persistent().set(&DataKey::Comment(parent_id), &parent);
+                Self::push_id(env, List::CommentReplies(parent_id), id);
+                parent.depth + 1
+            }
+            None => {
+                Self::push_id(env, List::PostComments(post_id), id);
+                0
+            }
+        };
+
+        let comment = Comment {
+            id,
+            post_id,
+            parent_comment_id,
+            anonymous: author.is_none(),
+            author,
+            author_commitment: author_commitment.map(Bytes::from),
+            content,
+            timestamp: env.ledger().timestamp(),
+            replies: 0,
+            deleted: false,
+            depth,
+            reactions: Map::new(env),
+            hidden: false,
+        };
+        env.storage().persistent().set(&DataKey::Comment(id), &comment);
+        env.storage().instance().set(&DataKey::CommentCount, &id);
+
+        post.comments += 1;
+        Self::write_post(env, &post);
+        Self::record_tag_activity(env, &post.tags, 0, 1);
+        comment
+    }
+
+    fn require_viewer(env: &Env, post: &Post, user: &Address) {
+        if !Self::can_view(env, post, &Some(user.clone())) {
+            panic_with_error!(env, PostError::PostNotFound);
+        }
+    }
+
+    fn target_exists(env: &Env, target: &Target) -> bool {
+        match target {
+            Target::Post(id) => Self::live_post(env, *id).is_some_and(|post| !post.deleted),
+            Target::Comment(id) => env
+                .storage()
+                .persistent()
+                .get::<_, Comment>(&DataKey::Comment(*id))
+                .is_some_and(|comment| !comment.deleted),
+        }
+    }
+
+    fn require_moderator(env: &Env, moderator: &Address) {
+        moderator.require_auth();
+        if !Self::get_moderators(env.clone()).contains(moderator) {
+            panic_with_error!(env, PostError::NotModerator);
+        }
+    }
+
+    fn require_admin(env: &Env) {
+        let admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .unwrap_or_else(|| panic_with_error!(env, PostError::NotInitialized));
+        admin.require_auth();
+    }
+
+    fn require_not_paused(env: &Env) {
+        if Self::is_paused(env.clone()) {
+            panic_with_error!(env, PostError::Paused);
+        }
     }
 }
+
+#[cfg(test)]
+#[path = "PostContract_test.rs"]
+mod test;
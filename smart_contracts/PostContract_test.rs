@@ -1,22 +1,93 @@
 #![cfg(test)]
+extern crate std;
 
 use super::*;
-use soroban_sdk::{Address, Env, Symbol};
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::token::StellarAssetClient;
+use soroban_sdk::{contract, contractimpl, symbol_short, vec, Address, Bytes, BytesN, Env, String, Symbol, Vec};
+use std::string::ToString;
+
+// Stand-in for the follow and match contracts with relationships set by the test
+#[contract]
+pub struct MockSocial;
+
+#[contractimpl]
+impl MockSocial {
+    pub fn follow(env: Env, follower: Address, followee: Address) {
+        env.storage().instance().set(&(symbol_short!("follow"), follower, followee), &true);
+    }
+
+    pub fn add_match(env: Env, user: Address, other: Address) {
+        env.storage().instance().set(&(symbol_short!("match"), user.clone(), other.clone()), &true);
+        env.storage().instance().set(&(symbol_short!("match"), other, user), &true);
+    }
+
+    pub fn is_following(env: Env, follower: Address, followee: Address) -> bool {
+        env.storage().instance().has(&(symbol_short!("follow"), follower, followee))
+    }
+
+    pub fn is_matched(env: Env, user: Address, other: Address) -> bool {
+        env.storage().instance().has(&(symbol_short!("match"), user, other))
+    }
+}
+
+// Stand-in for UserProfileContract that counts received likes and rejects events for `blocked`
+#[contract]
+pub struct MockAnalytics;
+
+#[contractimpl]
+impl MockAnalytics {
+    pub fn block(env: Env, user: Address) {
+        env.storage().instance().set(&(symbol_short!("blocked"), user), &true);
+    }
+
+    pub fn record_event(env: Env, reporter: Address, user: Address, event: AnalyticsEvent) {
+        reporter.require_auth();
+        assert_eq!(event, AnalyticsEvent::LikeReceived);
+        assert!(!env.storage().instance().has(&(symbol_short!("blocked"), user.clone())));
+        let count = env.storage().instance().get::<_, u32>(&user).unwrap_or(0);
+        env.storage().instance().set(&user, &(count + 1));
+    }
+
+    pub fn likes_received(env: Env, user: Address) -> u32 {
+        env.storage().instance().get(&user).unwrap_or(0)
+    }
+}
+
+fn setup(env: &Env) -> PostContractClient<'_> {
+    env.mock_all_auths();
+    let client = PostContractClient::new(env, &env.register_contract(None, PostContract));
+    client.initialize(&Address::generate(env));
+    client
+}
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn text(env: &Env, value: &str) -> String {
+    String::from_str(env, value)
+}
+
+#[test]
+fn test_initialize_requires_admin_auth() {
+    let env = Env::default();
+    let client = PostContractClient::new(&env, &env.register_contract(None, PostContract));
+    assert!(client.try_initialize(&Address::generate(&env)).is_err());
+}
 
 #[test]
 fn test_create_post() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
-    
-    let post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Hello, this is my first anonymous post!"),
-        vec![&env, symbol_short!("general"), symbol_short!("introduction")]
+
+    let post = client.create_post(
+        &author,
+        &text(&env, "Hello, this is my first anonymous post!"),
+        &vec![&env, symbol_short!("general"), Symbol::new(&env, "introduction")],
     );
-    
-    assert_eq!(post.author, author);
-    assert_eq!(post.content, symbol_short!("Hello, this is my first anonymous post!"));
+
+    assert_eq!(post.author, Some(author));
+    assert_eq!(post.content, text(&env, "Hello, this is my first anonymous post!"));
     assert_eq!(post.likes, 0);
     assert_eq!(post.comments, 0);
     assert_eq!(post.tags.len(), 2);
@@ -26,35 +97,79 @@ fn test_create_post() {
 #[test]
 fn test_create_anonymous_post() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
-    
-    let post = PostContract::create_anonymous_post(
-        &env,
-        author.clone(),
-        symbol_short!("This is an anonymous post about blockchain technology"),
-        vec![&env, symbol_short!("blockchain"), symbol_short!("technology")]
+    let salt = BytesN::from_array(&env, &[7; 32]);
+    let commitment = author_commitment(&env, &author, &salt);
+
+    let post = client.create_anonymous_post(
+        &commitment,
+        &text(&env, "This is an anonymous post about blockchain technology"),
+        &vec![&env, Symbol::new(&env, "blockchain"), Symbol::new(&env, "technology")],
     );
-    
-    assert_eq!(post.author, author);
-    assert_eq!(post.content, symbol_short!("This is an anonymous post about blockchain technology"));
+
+    // The ledger only links the post to a commitment, never to the address
+    assert_eq!(post.author, None);
+    assert_eq!(post.author_commitment, Some(Bytes::from(commitment)));
+    assert_eq!(client.get_post(&post.id, &None).unwrap().author, None);
+    assert_eq!(post.content, text(&env, "This is an anonymous post about blockchain technology"));
     assert!(post.anonymous);
     assert_eq!(post.tags.len(), 2);
+    assert_eq!(client.get_user_posts(&author, &None, &0, &10).len(), 0);
 }
 
 #[test]
-fn test_get_post() {
+fn test_claim_anonymous_post() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let impostor = Address::generate(&env);
+    let salt = BytesN::from_array(&env, &[7; 32]);
+
+    let post = client.create_anonymous_post(
+        &author_commitment(&env, &author, &salt),
+        &text(&env, "Guess who"),
+        &vec![&env, symbol_short!("test")],
+    );
+
+    // Wrong address or wrong salt does not match the commitment
+    assert!(client.try_claim_post(&post.id, &impostor, &salt).is_err());
+    assert!(client.try_claim_post(&post.id, &author, &BytesN::from_array(&env, &[8; 32])).is_err());
+
+    let claimed = client.claim_post(&post.id, &author, &salt);
+    assert_eq!(claimed.author, Some(author.clone()));
+    assert_eq!(client.get_user_posts(&author, &None, &0, &10).len(), 1);
+}
+
+#[test]
+fn test_delete_anonymous_post() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
-    
-    let created_post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Test post content"),
-        vec![&env, symbol_short!("test")]
+    let salt = BytesN::from_array(&env, &[7; 32]);
+
+    let post = client.create_anonymous_post(
+        &author_commitment(&env, &author, &salt),
+        &text(&env, "Post to delete"),
+        &vec![&env, symbol_short!("test")],
     );
-    
-    let retrieved_post = PostContract::get_post(&env, created_post.id).unwrap();
-    
+
+    assert!(client.try_delete_post(&post.id, &author, &None).is_err());
+    client.delete_post(&post.id, &author, &Some(salt));
+    assert!(client.get_post(&post.id, &None).is_none());
+}
+
+#[test]
+fn test_get_post() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+
+    let created_post =
+        client.create_post(&author, &text(&env, "Test post content"), &vec![&env, symbol_short!("test")]);
+
+    let retrieved_post = client.get_post(&created_post.id, &None).unwrap();
+
     assert_eq!(retrieved_post.id, created_post.id);
     assert_eq!(retrieved_post.author, created_post.author);
     assert_eq!(retrieved_post.content, created_post.content);
@@ -65,250 +180,1178 @@ fn test_get_post() {
 #[test]
 fn test_get_nonexistent_post() {
     let env = Env::default();
-    
-    let post = PostContract::get_post(&env, 999);
+    let client = setup(&env);
+
+    let post = client.get_post(&999, &None);
     assert!(post.is_none());
 }
 
 #[test]
 fn test_like_post() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
     let liker = Address::generate(&env);
-    
-    let post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Post to like"),
-        vec![&env, symbol_short!("test")]
-    );
-    
+
+    let post = client.create_post(&author, &text(&env, "Post to like"), &vec![&env, symbol_short!("test")]);
+
     assert_eq!(post.likes, 0);
-    
-    let liked_post = PostContract::like_post(&env, post.id, liker.clone());
+
+    let liked_post = client.like_post(&post.id, &liker).unwrap();
     assert_eq!(liked_post.likes, 1);
-    
-    // Like again (should increment)
-    let liked_post_again = PostContract::like_post(&env, post.id, liker.clone());
-    assert_eq!(liked_post_again.likes, 2);
+
+    // Like again (one reaction per user, so the count stays put)
+    let liked_post_again = client.like_post(&post.id, &liker).unwrap();
+    assert_eq!(liked_post_again.likes, 1);
+    assert_eq!(liked_post_again.reactions.get(symbol_short!("like")), Some(1));
+}
+
+#[test]
+fn test_reactions() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let fan = Address::generate(&env);
+
+    let post = client.create_post(&author, &text(&env, "React to me"), &vec![&env, symbol_short!("test")]);
+    let comment = client.add_comment(&post.id, &fan, &text(&env, "Same"), &None);
+    let target = Target::Post(post.id);
+
+    let secret = BytesN::from_array(&env, &[7; 32]);
+    let commitment = reaction_commitment(&env, &target, &secret);
+    client.react(&target, &fan, &symbol_short!("love"));
+    client.react_anonymously(&target, &commitment, &symbol_short!("love"));
+    let counts = client.react(&target, &fan, &symbol_short!("haha"));
+    assert_eq!(counts.get(symbol_short!("love")), Some(1));
+    assert_eq!(counts.get(symbol_short!("haha")), Some(1));
+    assert_eq!(client.get_post(&post.id, &None).unwrap().reactions, counts);
+
+    // Comments keep their own counts
+    client.react(&Target::Comment(comment.id), &author, &symbol_short!("wow"));
+    assert_eq!(client.get_comment(&comment.id, &None).unwrap().reactions.get(symbol_short!("wow")), Some(1));
+
+    // Anonymous reactors are hidden
+    let reactors = client.get_reactors(&target, &0, &10);
+    assert_eq!(reactors.len(), 2);
+    assert_eq!(reactors.get(0).unwrap(), Reactor { user: Some(fan.clone()), reaction: symbol_short!("haha") });
+    assert_eq!(reactors.get(1).unwrap(), Reactor { user: None, reaction: symbol_short!("love") });
+
+    // A commitment holds one reaction, and only its secret removes it
+    assert_eq!(client.get_anonymous_reaction(&target, &commitment).unwrap().reaction, symbol_short!("love"));
+    assert!(client.try_react_anonymously(&target, &commitment, &symbol_short!("haha")).is_err());
+    client.unreact_anonymously(&target, &BytesN::from_array(&env, &[8; 32]));
+    assert_eq!(client.get_post(&post.id, &None).unwrap().reactions.get(symbol_short!("love")), Some(1));
+
+    let counts = client.unreact(&target, &fan);
+    assert_eq!(counts.get(symbol_short!("haha")), None);
+    assert_eq!(client.get_reactors(&target, &0, &10).len(), 1);
+
+    // Only known reaction types, which the admin can extend
+    assert!(client.try_react(&target, &fan, &symbol_short!("fire")).is_err());
+    client.add_reaction_type(&symbol_short!("fire"));
+    client.react(&target, &fan, &symbol_short!("fire"));
+    assert_eq!(client.get_reaction(&target, &fan).unwrap().reaction, symbol_short!("fire"));
+
+    let counts = client.unreact_anonymously(&target, &secret);
+    assert_eq!(counts.get(symbol_short!("love")), None);
+    assert_eq!(client.get_anonymous_reaction(&target, &commitment), None);
+}
+
+// Whether the address appears anywhere in the ledger, in a key or a value
+fn stored_anywhere(env: &Env, address: &Address) -> bool {
+    let mut snapshot = std::vec::Vec::new();
+    env.to_ledger_snapshot().write(&mut snapshot).unwrap();
+    let snapshot = std::string::String::from_utf8(snapshot).unwrap();
+    let xdr = address.clone().to_xdr(env);
+    let id: std::string::String =
+        (xdr.len() - 32..xdr.len()).map(|i| std::format!("{:02x}", xdr.get_unchecked(i))).collect();
+    snapshot.contains(&address.to_string().to_string()) || snapshot.contains(&id)
+}
+
+#[test]
+fn test_anonymous_interactions_leave_no_address() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let lurker = Address::generate(&env);
+    let post = client.create_post(&author, &text(&env, "React to me"), &vec![&env, symbol_short!("test")]);
+    let comment = client.add_comment(&post.id, &author, &text(&env, "Me too"), &None);
+    assert!(stored_anywhere(&env, &author));
+
+    for (target, secret) in [(Target::Post(post.id), [1; 32]), (Target::Comment(comment.id), [2; 32])] {
+        let commitment = reaction_commitment(&env, &target, &BytesN::from_array(&env, &secret));
+        client.react_anonymously(&target, &commitment, &symbol_short!("like"));
+        assert!(env.auths().is_empty());
+    }
+    let commitment = author_commitment(&env, &lurker, &BytesN::from_array(&env, &[3; 32]));
+    client.add_anonymous_comment(&post.id, &commitment, &text(&env, "Psst"), &Some(comment.id));
+    assert!(env.auths().is_empty());
+    assert!(!stored_anywhere(&env, &lurker));
 }
 
 #[test]
 fn test_unlike_post() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
     let liker = Address::generate(&env);
-    
-    let post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Post to unlike"),
-        vec![&env, symbol_short!("test")]
-    );
-    
+
+    let post = client.create_post(&author, &text(&env, "Post to unlike"), &vec![&env, symbol_short!("test")]);
+
     // Like the post first
-    PostContract::like_post(&env, post.id, liker.clone());
-    
+    client.like_post(&post.id, &liker);
+
     // Unlike the post
-    let unliked_post = PostContract::unlike_post(&env, post.id, liker.clone());
+    let unliked_post = client.unlike_post(&post.id, &liker).unwrap();
     assert_eq!(unliked_post.likes, 0);
 }
 
 #[test]
 fn test_add_comment() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
     let commenter = Address::generate(&env);
-    
-    let post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Post with comments"),
-        vec![&env, symbol_short!("test")]
-    );
-    
+
+    let post = client.create_post(&author, &text(&env, "Post with comments"), &vec![&env, symbol_short!("test")]);
+
     assert_eq!(post.comments, 0);
-    
-    let comment = PostContract::add_comment(
-        &env,
-        post.id,
-        commenter.clone(),
-        symbol_short!("Great post!"),
-        false
-    );
-    
+
+    let comment = client.add_comment(&post.id, &commenter, &text(&env, "Great post!"), &None);
+
     assert_eq!(comment.post_id, post.id);
-    assert_eq!(comment.author, commenter);
-    assert_eq!(comment.content, symbol_short!("Great post!"));
+    assert_eq!(comment.author, Some(commenter));
+    assert_eq!(comment.content, text(&env, "Great post!"));
     assert!(!comment.anonymous);
-    
+
     // Check that post comment count increased
-    let updated_post = PostContract::get_post(&env, post.id).unwrap();
+    let updated_post = client.get_post(&post.id, &None).unwrap();
     assert_eq!(updated_post.comments, 1);
 }
 
 #[test]
 fn test_add_anonymous_comment() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
     let commenter = Address::generate(&env);
-    
-    let post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Post for anonymous comment"),
-        vec![&env, symbol_short!("test")]
-    );
-    
-    let comment = PostContract::add_comment(
-        &env,
-        post.id,
-        commenter.clone(),
-        symbol_short!("Anonymous comment"),
-        true
-    );
-    
+
+    let post =
+        client.create_post(&author, &text(&env, "Post for anonymous comment"), &vec![&env, symbol_short!("test")]);
+
+    let salt = BytesN::from_array(&env, &[2; 32]);
+    let commitment = author_commitment(&env, &commenter, &salt);
+    let comment = client.add_anonymous_comment(&post.id, &commitment, &text(&env, "Anonymous comment"), &None);
+    assert!(env.auths().is_empty());
+
     assert!(comment.anonymous);
-    assert_eq!(comment.content, symbol_short!("Anonymous comment"));
+    assert_eq!(comment.author, None);
+    assert_eq!(comment.author_commitment, Some(Bytes::from(commitment)));
+    assert_eq!(comment.content, text(&env, "Anonymous comment"));
+
+    // Only the salt behind the commitment proves authorship
+    assert!(client.try_delete_comment(&comment.id, &commenter, &None).is_err());
+    client.delete_comment(&comment.id, &commenter, &Some(salt));
+    assert!(client.get_comment(&comment.id, &None).is_none());
 }
 
 #[test]
 fn test_get_post_comments() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
     let commenter1 = Address::generate(&env);
     let commenter2 = Address::generate(&env);
-    
-    let post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Post with multiple comments"),
-        vec![&env, symbol_short!("test")]
-    );
-    
-    PostContract::add_comment(&env, post.id, commenter1.clone(), symbol_short!("First comment"), false);
-    PostContract::add_comment(&env, post.id, commenter2.clone(), symbol_short!("Second comment"), false);
-    
-    let comments = PostContract::get_post_comments(&env, post.id);
+
+    let post =
+        client.create_post(&author, &text(&env, "Post with multiple comments"), &vec![&env, symbol_short!("test")]);
+
+    client.add_comment(&post.id, &commenter1, &text(&env, "First comment"), &None);
+    client.add_comment(&post.id, &commenter2, &text(&env, "Second comment"), &None);
+
+    let comments = client.get_post_comments(&post.id, &None, &None, &1, &None, &10).0;
     assert_eq!(comments.len(), 2);
-    
-    assert_eq!(comments[0].content, symbol_short!("First comment"));
-    assert_eq!(comments[1].content, symbol_short!("Second comment"));
+
+    assert_eq!(comments.get(0).unwrap().content, text(&env, "First comment"));
+    assert_eq!(comments.get(1).unwrap().content, text(&env, "Second comment"));
+}
+
+#[test]
+fn test_threaded_comments() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let commenter = Address::generate(&env);
+
+    let post = client.create_post(&author, &text(&env, "Threads"), &vec![&env, symbol_short!("test")]);
+    let first = client.add_comment(&post.id, &commenter, &text(&env, "First"), &None);
+    let reply = client.add_comment(&post.id, &author, &text(&env, "Reply"), &Some(first.id));
+    let nested = client.add_comment(&post.id, &commenter, &text(&env, "Nested"), &Some(reply.id));
+    let second = client.add_comment(&post.id, &commenter, &text(&env, "Second"), &None);
+
+    assert_eq!(reply.parent_comment_id, Some(first.id));
+    assert_eq!(nested.depth, 2);
+    assert_eq!(client.get_comment(&first.id, &None).unwrap().replies, 1);
+    assert_eq!(client.get_post(&post.id, &None).unwrap().comments, 4);
+
+    // Depth-first, bounded by depth
+    let thread = client.get_post_comments(&post.id, &None, &None, &3, &None, &10).0;
+    let mut ids = vec![&env];
+    for comment in thread.iter() {
+        ids.push_back(comment.id);
+    }
+    assert_eq!(ids, vec![&env, first.id, reply.id, nested.id, second.id]);
+    assert_eq!(client.get_post_comments(&post.id, &None, &None, &2, &None, &10).0.len(), 3);
+
+    // Pages continue from the returned cursor
+    let (page, cursor) = client.get_post_comments(&post.id, &None, &None, &3, &None, &2);
+    assert_eq!(page.len(), 2);
+    assert_eq!(cursor, Some(nested.id));
+    let (page, cursor) = client.get_post_comments(&post.id, &None, &None, &3, &cursor, &2);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get(1).unwrap().id, second.id);
+    assert_eq!(cursor, None);
+    let subtree = client.get_post_comments(&post.id, &None, &Some(first.id), &2, &None, &10).0;
+    assert_eq!(subtree.len(), 2);
+    assert_eq!(subtree.get(0).unwrap().id, reply.id);
+
+    // Replies must target a comment on the same post
+    let other = client.create_post(&author, &text(&env, "Other"), &vec![&env, symbol_short!("test")]);
+    assert!(client.try_add_comment(&other.id, &commenter, &text(&env, "x"), &Some(first.id)).is_err());
+}
+
+#[test]
+fn test_comment_thread_cursor() {
+    let env = Env::default();
+    env.budget().reset_unlimited();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+
+    let post = client.create_post(&author, &text(&env, "Busy"), &vec![&env, symbol_short!("test")]);
+    let first = client.add_comment(&post.id, &author, &text(&env, "First"), &None);
+    let mut last_reply = first.clone();
+    for _ in 0..MAX_THREAD_COMMENTS {
+        last_reply = client.add_comment(&post.id, &author, &text(&env, "Reply"), &Some(first.id));
+    }
+    let second = client.add_comment(&post.id, &author, &text(&env, "Second"), &None);
+
+    // The first call stops at the cap; the cursor resumes inside the first thread and moves on to the second
+    let (page, cursor) = client.get_post_comments(&post.id, &None, &None, &2, &None, &u32::MAX);
+    assert_eq!(page.len(), MAX_THREAD_COMMENTS);
+    assert_eq!(cursor, Some(last_reply.id));
+    let (rest, cursor) = client.get_post_comments(&post.id, &None, &None, &2, &cursor, &u32::MAX);
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest.get_unchecked(0).id, last_reply.id);
+    assert_eq!(rest.get_unchecked(1).id, second.id);
+    assert_eq!(cursor, None);
+
+    // A cursor from another thread is rejected
+    let subtree = Some(second.id);
+    assert!(client.try_get_post_comments(&post.id, &None, &subtree, &2, &Some(last_reply.id), &10).is_err());
+}
+
+#[test]
+fn test_delete_comment_tombstones_parents() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let commenter = Address::generate(&env);
+
+    let post = client.create_post(&author, &text(&env, "Threads"), &vec![&env, symbol_short!("test")]);
+    let parent = client.add_comment(&post.id, &commenter, &text(&env, "Parent"), &None);
+    let reply = client.add_comment(&post.id, &author, &text(&env, "Reply"), &Some(parent.id));
+
+    assert!(client.try_delete_comment(&parent.id, &author, &None).is_err());
+    client.delete_comment(&parent.id, &commenter, &None);
+    let tombstone = client.get_comment(&parent.id, &None).unwrap();
+    assert!(tombstone.deleted);
+    assert_eq!(tombstone.content, text(&env, ""));
+    assert_eq!(client.get_post_comments(&post.id, &None, &None, &2, &None, &10).0.len(), 2);
+
+    // Leaf comments are removed outright, along with tombstones they leave without replies
+    let nested = client.add_comment(&post.id, &commenter, &text(&env, "Nested"), &Some(reply.id));
+    client.delete_comment(&reply.id, &author, &None);
+    client.delete_comment(&nested.id, &commenter, &None);
+    for id in [nested.id, reply.id, parent.id] {
+        assert!(client.get_comment(&id, &None).is_none());
+    }
+    assert_eq!(client.get_post_comments(&post.id, &None, &None, &2, &None, &10).0.len(), 0);
+    assert_eq!(client.get_post(&post.id, &None).unwrap().comments, 0);
+
+    // A tombstone with other replies stays
+    let parent = client.add_comment(&post.id, &commenter, &text(&env, "Parent"), &None);
+    let first = client.add_comment(&post.id, &author, &text(&env, "First"), &Some(parent.id));
+    client.add_comment(&post.id, &author, &text(&env, "Second"), &Some(parent.id));
+    client.delete_comment(&parent.id, &commenter, &None);
+    client.delete_comment(&first.id, &author, &None);
+    assert_eq!(client.get_comment(&parent.id, &None).unwrap().replies, 1);
 }
 
 #[test]
 fn test_search_posts() {
     let env = Env::default();
+    let client = setup(&env);
     let author1 = Address::generate(&env);
     let author2 = Address::generate(&env);
-    
-    PostContract::create_post(
-        &env,
-        author1.clone(),
-        symbol_short!("Post about blockchain technology"),
-        vec![&env, symbol_short!("blockchain"), symbol_short!("technology")]
+    let blockchain = Symbol::new(&env, "blockchain");
+
+    client.create_post(
+        &author1,
+        &text(&env, "Post about blockchain technology"),
+        &vec![&env, blockchain.clone(), Symbol::new(&env, "technology")],
     );
-    
-    PostContract::create_post(
-        &env,
-        author2.clone(),
-        symbol_short!("Post about cooking recipes"),
-        vec![&env, symbol_short!("cooking"), symbol_short!("recipes")]
+
+    client.create_post(
+        &author2,
+        &text(&env, "Post about cooking recipes"),
+        &vec![&env, symbol_short!("cooking"), symbol_short!("recipes")],
     );
-    
-    PostContract::create_post(
-        &env,
-        author1.clone(),
-        symbol_short!("Another post about blockchain"),
-        vec![&env, symbol_short!("blockchain"), symbol_short!("crypto")]
+
+    client.create_post(
+        &author1,
+        &text(&env, "Another post about blockchain"),
+        &vec![&env, blockchain.clone(), symbol_short!("crypto")],
     );
-    
+
     // Search for blockchain posts
-    let blockchain_posts = PostContract::search_posts(&env, symbol_short!("blockchain"));
+    let blockchain_posts = client.search_posts(&blockchain, &None, &0, &10);
     assert_eq!(blockchain_posts.len(), 2);
-    
+
     // Search for cooking posts
-    let cooking_posts = PostContract::search_posts(&env, symbol_short!("cooking"));
+    let cooking_posts = client.search_posts(&symbol_short!("cooking"), &None, &0, &10);
     assert_eq!(cooking_posts.len(), 1);
 }
 
 #[test]
 fn test_get_user_posts() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
-    
-    PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("First post"),
-        vec![&env, symbol_short!("first")]
-    );
-    
-    PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Second post"),
-        vec![&env, symbol_short!("second")]
-    );
-    
-    PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Third post"),
-        vec![&env, symbol_short!("third")]
-    );
-    
-    let user_posts = PostContract::get_user_posts(&env, author.clone());
+
+    client.create_post(&author, &text(&env, "First post"), &vec![&env, symbol_short!("first")]);
+
+    client.create_post(&author, &text(&env, "Second post"), &vec![&env, symbol_short!("second")]);
+
+    client.create_post(&author, &text(&env, "Third post"), &vec![&env, symbol_short!("third")]);
+
+    let user_posts = client.get_user_posts(&author, &None, &0, &10);
     assert_eq!(user_posts.len(), 3);
-    
+
     // Verify all posts belong to the same author
     for post in user_posts.iter() {
-        assert_eq!(post.author, author);
+        assert_eq!(post.author, Some(author.clone()));
     }
 }
 
 #[test]
 fn test_delete_post() {
     let env = Env::default();
+    let client = setup(&env);
     let author = Address::generate(&env);
-    
-    let post = PostContract::create_post(
-        &env,
-        author.clone(),
-        symbol_short!("Post to delete"),
-        vec![&env, symbol_short!("test")]
-    );
-    
+
+    let post = client.create_post(&author, &text(&env, "Post to delete"), &vec![&env, symbol_short!("test")]);
+
     // Verify post exists
-    assert!(PostContract::get_post(&env, post.id).is_some());
-    
+    assert!(client.get_post(&post.id, &None).is_some());
+
     // Delete post
-    PostContract::delete_post(&env, post.id, author.clone());
-    
+    client.delete_post(&post.id, &author, &None);
+
     // Verify post is deleted
-    assert!(PostContract::get_post(&env, post.id).is_none());
+    assert!(client.get_post(&post.id, &None).is_none());
 }
 
 #[test]
-fn test_get_all_posts() {
+fn test_edit_post() {
     let env = Env::default();
-    let author1 = Address::generate(&env);
-    let author2 = Address::generate(&env);
-    
-    PostContract::create_post(
-        &env,
-        author1.clone(),
-        symbol_short!("Post 1"),
-        vec![&env, symbol_short!("test")]
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let stranger = Address::generate(&env);
+
+    let post = client.create_post(&author, &text(&env, "Helo"), &vec![&env, symbol_short!("test")]);
+    assert!(!post.edited);
+    assert!(client.try_edit_post(&post.id, &stranger, &None, &text(&env, "Hijacked")).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp += 60);
+    let edited = client.edit_post(&post.id, &author, &None, &text(&env, "Hello"));
+    assert!(edited.edited);
+    assert_eq!(client.get_post(&post.id, &None).unwrap().content, text(&env, "Hello"));
+
+    let revisions = client.get_post_revisions(&post.id, &None);
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions.get(0).unwrap(), Revision { content: text(&env, "Helo"), timestamp: post.timestamp });
+    assert_eq!(revisions.get(1).unwrap(), Revision { content: text(&env, "Hello"), timestamp: post.timestamp + 60 });
+
+    // The history is capped
+    for _ in 2..MAX_REVISIONS {
+        client.edit_post(&post.id, &author, &None, &text(&env, "Hello"));
+    }
+    assert!(client.try_edit_post(&post.id, &author, &None, &text(&env, "Hello?")).is_err());
+    assert_eq!(client.get_post_revisions(&post.id, &None).len(), MAX_REVISIONS);
+
+    // Closed once the window has passed
+    client.set_edit_window(&120);
+    env.ledger().with_mut(|li| li.timestamp += 61);
+    assert!(client.try_edit_post(&post.id, &author, &None, &text(&env, "Hello!")).is_err());
+    assert_eq!(client.get_post_revisions(&post.id, &None).len(), MAX_REVISIONS);
+
+    // Hidden posts cannot be edited, and earlier wording is gone with the post
+    client.set_moderation_contract(&Address::generate(&env));
+    client.apply_moderation(&Target::Post(post.id), &true);
+    assert!(client.get_post_revisions(&post.id, &None).is_empty());
+    let fresh = client.create_post(&author, &text(&env, "Fresh"), &vec![&env, symbol_short!("test")]);
+    client.apply_moderation(&Target::Post(fresh.id), &true);
+    assert!(client.try_edit_post(&fresh.id, &author, &None, &text(&env, "Fresher")).is_err());
+    client.apply_moderation(&Target::Post(fresh.id), &false);
+    client.edit_post(&fresh.id, &author, &None, &text(&env, "Fresher"));
+    client.apply_moderation(&Target::Post(post.id), &false);
+    client.delete_post(&post.id, &author, &None);
+    assert!(client.get_post_revisions(&post.id, &None).is_empty());
+    assert!(!env.as_contract(&client.address, || env.storage().persistent().has(&DataKey::Revisions(post.id))));
+}
+
+fn feed_ids(env: &Env, page: &(Vec<Post>, Option<FeedCursor>)) -> Vec<u64> {
+    let mut ids = vec![env];
+    for post in page.0.iter() {
+        ids.push_back(post.id);
+    }
+    ids
+}
+
+#[test]
+fn test_ranked_feeds() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let tags = vec![&env, symbol_short!("test")];
+
+    let old = client.create_post(&author, &text(&env, "Old"), &tags);
+    env.ledger().with_mut(|li| li.timestamp += DAY);
+    let popular = client.create_post(&author, &text(&env, "Popular"), &tags);
+    let fresh = client.create_post(&author, &text(&env, "Fresh"), &tags);
+    for _ in 0..4 {
+        client.like_post(&old.id, &Address::generate(&env));
+    }
+    for _ in 0..2 {
+        client.like_post(&popular.id, &Address::generate(&env));
+    }
+
+    let newest = client.get_feed(&FeedMode::Newest, &None, &None, &10);
+    assert_eq!(feed_ids(&env, &newest), vec![&env, fresh.id, popular.id, old.id]);
+    assert_eq!(newest.1, None);
+
+    // All-time top versus the last hour
+    let top = client.get_feed(&FeedMode::Top(2 * DAY), &None, &None, &10);
+    assert_eq!(feed_ids(&env, &top), vec![&env, old.id, popular.id, fresh.id]);
+    let top_recent = client.get_feed(&FeedMode::Top(60 * 60), &None, &None, &10);
+    assert_eq!(feed_ids(&env, &top_recent), vec![&env, popular.id, fresh.id]);
+
+    // A day of age outweighs four likes
+    let hot = client.get_feed(&FeedMode::Hot, &None, &None, &10);
+    assert_eq!(feed_ids(&env, &hot), vec![&env, popular.id, fresh.id, old.id]);
+
+    // Deleted posts leave every index
+    client.delete_post(&popular.id, &author, &None);
+    let hot = client.get_feed(&FeedMode::Hot, &None, &None, &10);
+    assert_eq!(feed_ids(&env, &hot), vec![&env, fresh.id, old.id]);
+}
+
+#[test]
+fn test_feed_cursor_pagination() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+
+    for _ in 0..5 {
+        client.create_post(&author, &text(&env, "Post"), &vec![&env, symbol_short!("test")]);
+        env.ledger().with_mut(|li| li.timestamp += 1);
+    }
+
+    let first = client.get_feed(&FeedMode::Newest, &None, &None, &2);
+    assert_eq!(feed_ids(&env, &first), vec![&env, 5, 4]);
+    let second = client.get_feed(&FeedMode::Newest, &None, &first.1, &2);
+    assert_eq!(feed_ids(&env, &second), vec![&env, 3, 2]);
+    let last = client.get_feed(&FeedMode::Newest, &None, &second.1, &2);
+    assert_eq!(feed_ids(&env, &last), vec![&env, 1]);
+    assert_eq!(last.1, None);
+}
+
+// Soroban's per-transaction ledger read limit is 200KB; feed indexes should use at most half of it
+const FEED_READ_BUDGET: u32 = 100 * 1024;
+
+#[test]
+fn test_feed_index_cost() {
+    let env = Env::default();
+    env.budget().reset_unlimited();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    for _ in 0..=FEED_INDEX_CAP {
+        let post = client.create_post(&author, &text(&env, "Filler"), &vec![&env, symbol_short!("test")]);
+        client.like_post(&post.id, &Address::generate(&env));
+    }
+
+    // A full index, and the most a Top read can load: every day bucket of the longest window
+    let (index, top) = env.as_contract(&client.address, || {
+        let storage = env.storage().persistent();
+        let newest: Vec<FeedEntry> = storage.get(&DataKey::FeedNewest).unwrap();
+        let top: Vec<FeedEntry> = storage.get(&DataKey::FeedTop(0)).unwrap();
+        (newest, top)
+    });
+    assert_eq!(index.len(), FEED_INDEX_CAP);
+    assert_eq!(top.len(), FEED_INDEX_CAP);
+    let index_bytes = index.to_xdr(&env).len();
+    let top_buckets = (MAX_TOP_WINDOW / TOP_BUCKET + 1) as u32;
+    assert!(index_bytes * top_buckets <= FEED_READ_BUDGET, "{index_bytes} bytes per index");
+}
+
+#[test]
+fn test_feed_scan_cap() {
+    let env = Env::default();
+    env.budget().reset_unlimited();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    let social = MockSocialClient::new(&env, &env.register_contract(None, MockSocial));
+    client.set_follow_contract(&social.address);
+
+    let public = client.create_post(&author, &text(&env, "Public"), &vec![&env, symbol_short!("test")]);
+    // Spread over as many later days as it takes to keep every post in its day's top index
+    for i in 0..MAX_FEED_SCAN {
+        env.ledger().with_mut(|li| li.timestamp = DAY * (1 + (i / FEED_INDEX_CAP) as u64) + i as u64);
+        let secret = Bytes::from_array(&env, &[1; 16]);
+        client.create_scoped_post(&author, &secret, &vec![&env, symbol_short!("test")], &Visibility::Followers);
+    }
+
+    // Each scoped post costs a follow lookup, so a call gets through half as many of them
+    // and hands back a cursor to resume from
+    let mode = FeedMode::Top(MAX_TOP_WINDOW);
+    let first = client.get_feed(&mode, &Some(stranger.clone()), &None, &10);
+    assert!(first.0.is_empty());
+    let second = client.get_feed(&mode, &Some(stranger.clone()), &first.1, &10);
+    assert!(second.0.is_empty());
+    let third = client.get_feed(&mode, &Some(stranger), &second.1, &10);
+    assert_eq!(feed_ids(&env, &third), vec![&env, public.id]);
+    assert_eq!(third.1, None);
+
+    // The author needs no lookups and gets a full page at once
+    assert_eq!(client.get_feed(&mode, &Some(author), &None, &10).0.len(), 10);
+}
+
+#[test]
+fn test_trending_tags() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let rust = symbol_short!("rust");
+    let music = symbol_short!("music");
+
+    // Yesterday's favourite
+    for _ in 0..3 {
+        client.create_post(&author, &text(&env, "Old news"), &vec![&env, music.clone()]);
+    }
+    env.ledger().with_mut(|li| li.timestamp += DAY);
+
+    let post = client.create_post(&author, &text(&env, "Borrow checker"), &vec![&env, rust.clone(), music.clone()]);
+    let fan = Address::generate(&env);
+    client.like_post(&post.id, &fan);
+    // Switching or repeating a reaction does not count as more engagement
+    client.react(&Target::Post(post.id), &fan, &symbol_short!("love"));
+    client.unreact(&Target::Post(post.id), &fan);
+    client.like_post(&post.id, &fan);
+    client.add_comment(&post.id, &Address::generate(&env), &text(&env, "Same"), &None);
+    client.create_post(&author, &text(&env, "Lifetimes"), &vec![&env, rust.clone()]);
+
+    let hour = client.trending_tags(&(60 * 60), &10);
+    assert_eq!(hour.len(), 2);
+    assert_eq!(hour.get(0).unwrap(), TagTrend { tag: rust.clone(), posts: 2, engagement: 2, score: 12 });
+    assert_eq!(hour.get(1).unwrap(), TagTrend { tag: music.clone(), posts: 1, engagement: 2, score: 7 });
+
+    let two_days = client.trending_tags(&(2 * DAY), &1);
+    assert_eq!(two_days.len(), 1);
+    assert_eq!(two_days.get(0).unwrap().tag, music.clone());
+
+    assert_eq!(client.get_tag_post_count(&music), 4);
+    client.delete_post(&post.id, &author, &None);
+    assert_eq!(client.get_tag_post_count(&music), 3);
+}
+
+#[test]
+fn test_reports_hide_content() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let trusted = Address::generate(&env);
+    let spam = symbol_short!("spam");
+
+    let post = client.create_post(&author, &text(&env, "Buy now"), &vec![&env, symbol_short!("test")]);
+    let target = Target::Post(post.id);
+    client.set_report_threshold(&4);
+    client.set_reporter_weight(&trusted, &2);
+
+    let reporter = Address::generate(&env);
+    client.report(&target, &reporter, &spam);
+    assert!(client.try_report(&target, &reporter, &spam).is_err());
+    client.report(&target, &Address::generate(&env), &spam);
+    assert_eq!(client.get_feed(&FeedMode::Newest, &None, &None, &10).0.len(), 1);
+
+    let state = client.report(&target, &trusted, &spam);
+    assert_eq!(state, ModerationState { reports: 3, weight: 4, status: ModerationStatus::Hidden });
+    assert!(client.get_post(&post.id, &None).unwrap().hidden);
+    assert_eq!(client.get_feed(&FeedMode::Newest, &None, &None, &10).0.len(), 0);
+    assert_eq!(client.search_posts(&symbol_short!("test"), &None, &0, &10).len(), 0);
+
+    // Hidden posts take no new reactions or comments
+    let fan = Address::generate(&env);
+    assert!(client.try_like_post(&post.id, &fan).is_err());
+    assert!(client.try_react(&target, &fan, &symbol_short!("love")).is_err());
+    assert!(client.try_add_comment(&post.id, &fan, &text(&env, "Hi"), &None).is_err());
+    assert!(client
+        .try_add_anonymous_comment(&post.id, &BytesN::from_array(&env, &[1; 32]), &text(&env, "Hi"), &None)
+        .is_err());
+
+    // Hidden comments drop out of threads
+    let other = client.create_post(&author, &text(&env, "Fine"), &vec![&env, symbol_short!("test")]);
+    let comment = client.add_comment(&other.id, &author, &text(&env, "Rude"), &None);
+    client.report(&Target::Comment(comment.id), &trusted, &spam);
+    client.report(&Target::Comment(comment.id), &Address::generate(&env), &spam);
+    client.report(&Target::Comment(comment.id), &Address::generate(&env), &spam);
+    assert_eq!(client.get_post_comments(&other.id, &None, &None, &1, &None, &10).0.len(), 0);
+    assert!(client.try_react(&Target::Comment(comment.id), &fan, &symbol_short!("love")).is_err());
+    assert!(client.try_add_comment(&other.id, &fan, &text(&env, "Hi"), &Some(comment.id)).is_err());
+}
+
+#[test]
+fn test_moderator_appeals() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let moderator = Address::generate(&env);
+    client.add_moderator(&moderator);
+    client.set_report_threshold(&1);
+
+    let post = client.create_post(&author, &text(&env, "Misunderstood"), &vec![&env, symbol_short!("test")]);
+    let target = Target::Post(post.id);
+    assert!(client.try_appeal(&target, &author, &None).is_err());
+    client.report(&target, &Address::generate(&env), &symbol_short!("spam"));
+
+    assert!(client.try_appeal(&target, &Address::generate(&env), &None).is_err());
+    client.appeal(&target, &author, &None);
+    assert_eq!(client.get_appeals(), vec![&env, target.clone()]);
+
+    assert!(client.try_resolve_appeal(&author, &target, &true).is_err());
+    let state = client.resolve_appeal(&moderator, &target, &true);
+    assert_eq!(state.status, ModerationStatus::Restored);
+    assert_eq!(client.get_appeals().len(), 0);
+
+    // Restored content stays visible under further reports
+    client.report(&target, &Address::generate(&env), &symbol_short!("spam"));
+    assert!(!client.get_post(&post.id, &None).unwrap().hidden);
+
+    // An upheld hide cannot be appealed again
+    let second = client.create_post(&author, &text(&env, "Actually spam"), &vec![&env, symbol_short!("test")]);
+    let second_target = Target::Post(second.id);
+    client.report(&second_target, &Address::generate(&env), &symbol_short!("spam"));
+    client.appeal(&second_target, &author, &None);
+    assert_eq!(client.resolve_appeal(&moderator, &second_target, &false).status, ModerationStatus::Upheld);
+    assert!(client.get_post(&second.id, &None).unwrap().hidden);
+    assert!(client.try_appeal(&second_target, &author, &None).is_err());
+}
+
+#[test]
+fn test_apply_moderation() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let post = client.create_post(&author, &text(&env, "Borderline"), &vec![&env, symbol_short!("test")]);
+    let target = Target::Post(post.id);
+
+    // Only the registered moderation contract may apply verdicts
+    assert!(client.try_apply_moderation(&target, &true).is_err());
+    client.set_moderation_contract(&Address::generate(&env));
+
+    assert_eq!(client.apply_moderation(&target, &true).status, ModerationStatus::Upheld);
+    assert!(client.get_post(&post.id, &None).unwrap().hidden);
+    assert_eq!(client.apply_moderation(&target, &false).status, ModerationStatus::Restored);
+    assert!(!client.get_post(&post.id, &None).unwrap().hidden);
+
+    // A verdict on deleted content is a no-op rather than a failure; its moderation record went with it
+    client.delete_post(&post.id, &author, &None);
+    assert_eq!(client.apply_moderation(&target, &true).status, ModerationStatus::Visible);
+}
+
+#[test]
+fn test_tip_post() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let fan = Address::generate(&env);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    StellarAssetClient::new(&env, &token).mint(&fan, &1_000);
+    let post = client.create_post(&author, &text(&env, "Worth a tip"), &vec![&env, symbol_short!("test")]);
+
+    client.tip_post(&post.id, &fan, &token, &300);
+    let tips = client.tip_post(&post.id, &fan, &token, &200);
+    assert_eq!(tips.get(token.clone()), Some(500));
+    assert_eq!(token::Client::new(&env, &token).balance(&author), 500);
+    assert!(client.get_escrowed_tips(&post.id).is_empty());
+    assert!(client.try_tip_post(&post.id, &fan, &token, &0).is_err());
+}
+
+#[test]
+fn test_anonymous_tips_escrow() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let fan = Address::generate(&env);
+    let salt = BytesN::from_array(&env, &[3; 32]);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    StellarAssetClient::new(&env, &token).mint(&fan, &1_000);
+    let post = client.create_anonymous_post(
+        &author_commitment(&env, &author, &salt),
+        &text(&env, "Anonymous but deserving"),
+        &vec![&env, symbol_short!("test")],
+    );
+
+    client.tip_post(&post.id, &fan, &token, &400);
+    assert_eq!(client.get_escrowed_tips(&post.id).get(token.clone()), Some(400));
+    assert_eq!(token::Client::new(&env, &token).balance(&client.address), 400);
+
+    // Only the committed author can release the escrow
+    assert!(client.try_claim_tips(&post.id, &fan, &salt).is_err());
+    assert!(client.try_claim_tips(&post.id, &author, &BytesN::from_array(&env, &[4; 32])).is_err());
+    client.claim_tips(&post.id, &author, &salt);
+    assert_eq!(token::Client::new(&env, &token).balance(&author), 400);
+    assert!(client.get_escrowed_tips(&post.id).is_empty());
+    assert_eq!(client.get_post_tips(&post.id).get(token.clone()), Some(400));
+
+    // Tips left in escrow are paid out when the author deletes the post
+    client.tip_post(&post.id, &fan, &token, &100);
+    client.delete_post(&post.id, &author, &Some(salt));
+    assert_eq!(token::Client::new(&env, &token).balance(&author), 500);
+}
+
+#[test]
+fn test_unclaimed_tips_refunded_on_expiry() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let (fan, other_fan) = (Address::generate(&env), Address::generate(&env));
+    let salt = BytesN::from_array(&env, &[3; 32]);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    StellarAssetClient::new(&env, &token).mint(&fan, &1_000);
+    StellarAssetClient::new(&env, &token).mint(&other_fan, &1_000);
+    let post = client.create_anonymous_post(
+        &author_commitment(&env, &author, &salt),
+        &text(&env, "Here today"),
+        &vec![&env, symbol_short!("test")],
     );
-    
-    PostContract::create_post(
-        &env,
-        author2.clone(),
-        symbol_short!("Post 2"),
-        vec![&env, symbol_short!("test")]
+    client.set_post_expiry(&post.id, &author, &Some(salt.clone()), &DAY);
+
+    client.tip_post(&post.id, &fan, &token, &300);
+    client.tip_post(&post.id, &fan, &token, &100);
+    client.tip_post(&post.id, &other_fan, &token, &50);
+
+    // Once expired the author can no longer claim, so purging refunds each tipper
+    env.ledger().with_mut(|l| l.timestamp = DAY);
+    assert!(client.try_claim_tips(&post.id, &author, &salt).is_err());
+    assert_eq!(client.purge_expired(&10), 1);
+    let balance = |user: &Address| token::Client::new(&env, &token).balance(user);
+    assert_eq!(balance(&fan), 1_000);
+    assert_eq!(balance(&other_fan), 1_000);
+    assert_eq!(balance(&client.address), 0);
+    assert!(client.get_escrowed_tips(&post.id).is_empty());
+}
+
+fn poll_options(env: &Env) -> Vec<String> {
+    vec![env, text(env, "Coffee"), text(env, "Tea"), text(env, "Neither")]
+}
+
+#[test]
+fn test_poll() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let tags = vec![&env, symbol_short!("poll")];
+    let (alice, bob) = (Address::generate(&env), Address::generate(&env));
+
+    let one_option = vec![&env, text(&env, "Only")];
+    assert!(client.try_create_poll(&author, &text(&env, "?"), &tags, &one_option, &DAY, &false).is_err());
+    let post = client.create_poll(&author, &text(&env, "Morning drink?"), &tags, &poll_options(&env), &DAY, &false);
+    assert!(post.poll);
+
+    client.vote_poll(&post.id, &alice, &0);
+    assert_eq!(client.vote_poll(&post.id, &bob, &1).voters, 2);
+    assert!(client.try_vote_poll(&post.id, &alice, &1).is_err());
+    assert!(client.try_vote_poll(&post.id, &Address::generate(&env), &3).is_err());
+    // Tallies stay unreadable until the poll closes
+    assert!(client.try_get_poll_results(&post.id).is_err());
+
+    env.ledger().with_mut(|l| l.timestamp = DAY);
+    assert!(client.try_vote_poll(&post.id, &Address::generate(&env), &0).is_err());
+    assert_eq!(client.get_poll_results(&post.id), vec![&env, 1, 1, 0]);
+}
+
+#[test]
+fn test_sealed_poll() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let voter = Address::generate(&env);
+    let salt = BytesN::from_array(&env, &[9; 32]);
+    let tags = vec![&env, symbol_short!("poll")];
+    let post = client.create_poll(&author, &text(&env, "Secret ballot"), &tags, &poll_options(&env), &DAY, &true);
+
+    // Sealed polls only take commitments while open
+    assert!(client.try_vote_poll(&post.id, &voter, &2).is_err());
+    client.commit_poll_vote(&post.id, &voter, &vote_commitment(&env, &voter, 2, &salt));
+    assert!(client.try_reveal_poll_vote(&post.id, &voter, &2, &salt).is_err());
+
+    env.ledger().with_mut(|l| l.timestamp = DAY);
+    assert_eq!(client.get_poll_results(&post.id), vec![&env, 0, 0, 0]);
+    assert!(client.try_reveal_poll_vote(&post.id, &voter, &1, &salt).is_err());
+    client.reveal_poll_vote(&post.id, &voter, &2, &salt);
+    assert!(client.try_reveal_poll_vote(&post.id, &voter, &2, &salt).is_err());
+    assert_eq!(client.get_poll_results(&post.id), vec![&env, 0, 0, 1]);
+}
+
+#[test]
+fn test_bookmarks() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let owner = Address::generate(&env);
+    let tags = vec![&env, symbol_short!("test")];
+    let saved = symbol_short!("saved");
+    let mut ids = vec![&env];
+    for _ in 0..4 {
+        ids.push_back(client.create_post(&author, &text(&env, "Keep this"), &tags).id);
+    }
+
+    for id in ids.iter() {
+        client.add_bookmark(&owner, &saved, &id);
+    }
+    client.add_bookmark(&owner, &saved, &ids.get_unchecked(0));
+    client.add_bookmark(&owner, &symbol_short!("later"), &ids.get_unchecked(3));
+    assert_eq!(client.get_bookmark_lists(&owner), vec![&env, saved.clone(), symbol_short!("later")]);
+    assert!(client.try_add_bookmark(&owner, &saved, &99).is_err());
+
+    // Listing needs the owner's authorization
+    let page = client.get_bookmarks(&owner, &saved, &0, &2);
+    assert_eq!(env.auths()[0].0, owner);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get_unchecked(1).id, ids.get_unchecked(1));
+
+    // Removed and deleted posts drop out of the list
+    client.remove_bookmark(&owner, &saved, &ids.get_unchecked(0));
+    client.delete_post(&ids.get_unchecked(2), &author, &None);
+    let page = client.get_bookmarks(&owner, &saved, &0, &10);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get_unchecked(0).id, ids.get_unchecked(1));
+    assert_eq!(page.get_unchecked(1).id, ids.get_unchecked(3));
+    assert!(client.get_bookmarks(&Address::generate(&env), &saved, &0, &10).is_empty());
+}
+
+#[test]
+fn test_lists_are_paged() {
+    let env = Env::default();
+    let client = setup(&env);
+    env.budget().reset_unlimited();
+    let author = Address::generate(&env);
+    let post = client.create_post(&author, &text(&env, "Busy"), &vec![&env, symbol_short!("test")]);
+    let target = Target::Post(post.id);
+    let mut fans = vec![&env];
+    for _ in 0..LIST_PAGE + 5 {
+        let fan = Address::generate(&env);
+        client.react(&target, &fan, &symbol_short!("like"));
+        fans.push_back(fan);
+    }
+
+    // Each page holds LIST_PAGE slots at most; removals empty a slot without moving later reactors
+    let list = List::Reactors(target.clone());
+    let pages = env.as_contract(&client.address, || {
+        [0, 1].map(|page| PostContract::list_page::<ReactorKey>(&env, &list, page).len())
+    });
+    assert_eq!(pages, [LIST_PAGE, 5]);
+    client.unreact(&target, &fans.get_unchecked(0));
+    client.unreact(&target, &fans.get_unchecked(LIST_PAGE));
+    let first = client.get_reactors(&target, &0, &LIST_PAGE);
+    assert_eq!(first.len(), LIST_PAGE - 1);
+    assert_eq!(first.get_unchecked(0).user, Some(fans.get_unchecked(1)));
+    let second = client.get_reactors(&target, &LIST_PAGE, &LIST_PAGE);
+    assert_eq!(second.len(), 4);
+    assert_eq!(second.get_unchecked(0).user, Some(fans.get_unchecked(LIST_PAGE + 1)));
+
+    // Reacting again takes a new slot at the end
+    client.react(&target, &fans.get_unchecked(0), &symbol_short!("wow"));
+    let last = client.get_reactors(&target, &LIST_PAGE, &LIST_PAGE);
+    assert_eq!(last.get_unchecked(4).user, Some(fans.get_unchecked(0)));
+
+    // Anonymous posts hold escrow for a bounded number of tippers
+    let post = client.create_anonymous_post(
+        &BytesN::from_array(&env, &[1; 32]),
+        &text(&env, "Tip jar"),
+        &vec![&env, symbol_short!("test")],
     );
-    
-    let all_posts = PostContract::get_all_posts(&env);
-    assert_eq!(all_posts.len(), 2);
-} 
\ No newline at end of file
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    for fan in fans.iter().take(MAX_ESCROW_TIPPERS as usize) {
+        StellarAssetClient::new(&env, &token).mint(&fan, &2);
+        client.tip_post(&post.id, &fan, &token, &1);
+    }
+    let fan = fans.get_unchecked(0);
+    client.tip_post(&post.id, &fan, &token, &1);
+    let latecomer = fans.get_unchecked(MAX_ESCROW_TIPPERS);
+    StellarAssetClient::new(&env, &token).mint(&latecomer, &1);
+    assert!(client.try_tip_post(&post.id, &latecomer, &token, &1).is_err());
+    assert_eq!(client.get_escrowed_tips(&post.id).get(token), Some(MAX_ESCROW_TIPPERS as i128 + 1));
+}
+
+#[test]
+fn test_reposts() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let fan = Address::generate(&env);
+    let original = client.create_post(&author, &text(&env, "Original"), &vec![&env, symbol_short!("test")]);
+
+    let repost = client.repost(&original.id, &fan);
+    assert_eq!(repost.repost_of, Some(original.id));
+    assert!(repost.content.is_empty());
+    assert!(client.try_repost(&original.id, &fan).is_err());
+    let quote = client.quote_post(&original.id, &fan, &text(&env, "So true"));
+    assert_eq!(quote.repost_of, Some(original.id));
+    assert_eq!(client.get_post(&original.id, &None).unwrap().reposts, 2);
+
+    // Undoing a repost lowers the count and allows reposting again
+    client.delete_post(&repost.id, &fan, &None);
+    assert_eq!(client.get_post(&original.id, &None).unwrap().reposts, 1);
+    client.repost(&original.id, &fan);
+
+    // The deleted original stays behind as a tombstone for its reposts
+    client.delete_post(&original.id, &author, &None);
+    let tombstone = client.get_post(&quote.repost_of.unwrap(), &None).unwrap();
+    assert!(tombstone.deleted);
+    assert!(tombstone.content.is_empty());
+    assert_eq!(tombstone.author, None);
+    assert!(client.try_delete_post(&original.id, &author, &None).is_err());
+    assert!(client.try_quote_post(&original.id, &fan, &text(&env, "Gone")).is_err());
+    assert_eq!(client.get_feed(&FeedMode::Newest, &None, &None, &10).0.len(), 2);
+}
+
+#[test]
+fn test_expiring_posts() {
+    let env = Env::default();
+    // Keep persistent entries alive past the point where expired posts' temporary entries lapse
+    env.ledger().with_mut(|l| l.min_persistent_entry_ttl = (20 * DAY / 5) as u32);
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let tag = symbol_short!("story");
+    let story = client.create_post(&author, &text(&env, "Gone tomorrow"), &vec![&env, tag.clone()]);
+    let lasting = client.create_post(&author, &text(&env, "Here to stay"), &vec![&env, tag.clone()]);
+
+    assert!(client.try_set_post_expiry(&story.id, &author, &None, &0).is_err());
+    assert!(client.try_set_post_expiry(&story.id, &author, &None, &(60 * DAY)).is_err());
+    let story = client.set_post_expiry(&story.id, &author, &None, &DAY);
+    assert_eq!(story.expires_at, Some(DAY));
+    let fan = Address::generate(&env);
+    client.like_post(&story.id, &fan);
+    assert_eq!(client.get_post(&story.id, &None).unwrap().likes, 1);
+    client.edit_post(&story.id, &author, &None, &text(&env, "Gone tomorrow!"));
+    let comment = client.add_comment(&story.id, &author, &text(&env, "Bye"), &None);
+    let target = Target::Comment(comment.id);
+    let commitment = reaction_commitment(&env, &target, &BytesN::from_array(&env, &[5; 32]));
+    client.react_anonymously(&target, &commitment, &symbol_short!("love"));
+    assert_eq!(client.search_posts(&tag, &None, &0, &10).len(), 2);
+
+    // Expired posts disappear from reads right away
+    env.ledger().with_mut(|l| l.timestamp = DAY);
+    assert_eq!(client.get_post(&story.id, &None), None);
+    assert_eq!(client.search_posts(&tag, &None, &0, &10).len(), 1);
+    assert_eq!(feed_ids(&env, &client.get_feed(&FeedMode::Newest, &None, &None, &10)), vec![&env, lasting.id]);
+    assert!(client.try_like_post(&story.id, &Address::generate(&env)).is_err());
+
+    // Anyone can purge them while the contract is not paused, along with everything stored for them
+    client.set_paused(&true);
+    assert!(client.try_purge_expired(&10).is_err());
+    client.set_paused(&false);
+    assert_eq!(client.purge_expired(&10), 1);
+    assert_eq!(client.get_tag_post_count(&tag), 1);
+    env.as_contract(&client.address, || {
+        let storage = env.storage().persistent();
+        for list in [List::UserPosts(author.clone()), List::TagPosts(tag.clone())] {
+            assert_eq!(PostContract::list_range::<u64>(&env, &list, 0, 10), vec![&env, lasting.id]);
+            assert!(!storage.has(&DataKey::ListSlot(list, story.id)));
+        }
+        let comments = List::PostComments(story.id);
+        let (post_reactors, comment_reactors) =
+            (List::Reactors(Target::Post(story.id)), List::Reactors(target.clone()));
+        for key in [
+            DataKey::Revisions(story.id),
+            DataKey::ListLen(comments.clone()),
+            DataKey::ListPage(comments.clone(), 0),
+            DataKey::ListSlot(comments, comment.id),
+            DataKey::Comment(comment.id),
+            DataKey::ListLen(post_reactors.clone()),
+            DataKey::ListPage(post_reactors, 0),
+            DataKey::Reaction(Target::Post(story.id), fan.clone()),
+            DataKey::ReactorSlot(Target::Post(story.id), ReactorKey::User(fan.clone())),
+            DataKey::AnonReaction(target.clone(), commitment.clone()),
+            DataKey::ReactorSlot(target.clone(), ReactorKey::Anonymous(commitment.clone())),
+            DataKey::ListLen(comment_reactors.clone()),
+            DataKey::ListPage(comment_reactors, 0),
+            DataKey::Engaged(story.id),
+            DataKey::Expiring(0),
+        ] {
+            assert!(!storage.has(&key));
+        }
+    });
+    assert_eq!(client.purge_expired(&10), 0);
+
+    // Later expiry days are only reached once they have passed
+    let later = client.create_post(&author, &text(&env, "Gone in three days"), &vec![&env, tag.clone()]);
+    client.set_post_expiry(&later.id, &author, &None, &(3 * DAY));
+    env.ledger().with_mut(|l| l.timestamp = 2 * DAY);
+    assert_eq!(client.purge_expired(&10), 0);
+    // Purging still cleans up once the post's temporary entry has lapsed
+    env.ledger().with_mut(|l| {
+        l.timestamp = 10 * DAY;
+        l.sequence_number += (10 * DAY / 5) as u32;
+    });
+    assert!(!env.as_contract(&client.address, || env.storage().temporary().has(&DataKey::Post(later.id))));
+    assert_eq!(client.purge_expired(&10), 1);
+    assert_eq!(client.get_tag_post_count(&tag), 1);
+    assert_eq!(client.search_posts(&tag, &None, &0, &10).len(), 1);
+    assert!(!env.as_contract(&client.address, || env.storage().persistent().has(&DataKey::Expiring(3))));
+}
+
+#[test]
+fn test_visibility_scopes() {
+    let env = Env::default();
+    let client = setup(&env);
+    let social = MockSocialClient::new(&env, &env.register_contract(None, MockSocial));
+    let author = Address::generate(&env);
+    let (follower, partner, stranger) = (Address::generate(&env), Address::generate(&env), Address::generate(&env));
+    let tags = vec![&env, symbol_short!("test")];
+    let secret = Bytes::from_array(&env, &[0xab; 16]);
+
+    assert!(client.try_create_scoped_post(&author, &secret, &tags, &Visibility::Public).is_err());
+    let public = client.create_post(&author, &text(&env, "Hello all"), &tags);
+    let for_followers = client.create_scoped_post(&author, &secret, &tags, &Visibility::Followers);
+    let for_matches = client.create_scoped_post(&author, &secret, &tags, &Visibility::Matches);
+    assert!(for_followers.content.is_empty());
+    assert_eq!(for_followers.ciphertext, Some(secret));
+
+    // Without follow and match contracts only the author sees scoped posts
+    assert_eq!(client.get_post(&for_followers.id, &Some(follower.clone())), None);
+    assert!(client.get_post(&for_matches.id, &Some(author.clone())).is_some());
+
+    client.set_follow_contract(&social.address);
+    client.set_match_contract(&social.address);
+    social.follow(&follower, &author);
+    social.add_match(&author, &partner);
+
+    assert!(client.get_post(&for_followers.id, &Some(follower.clone())).is_some());
+    assert_eq!(client.get_post(&for_matches.id, &Some(follower.clone())), None);
+    assert!(client.get_post(&for_matches.id, &Some(partner.clone())).is_some());
+    assert_eq!(client.get_post(&for_followers.id, &None), None);
+    assert!(client.get_post(&public.id, &None).is_some());
+
+    let ids = |viewer: &Address| feed_ids(&env, &client.get_feed(&FeedMode::Newest, &Some(viewer.clone()), &None, &10));
+    assert_eq!(ids(&author), vec![&env, for_matches.id, for_followers.id, public.id]);
+    assert_eq!(ids(&follower), vec![&env, for_followers.id, public.id]);
+    assert_eq!(ids(&partner), vec![&env, for_matches.id, public.id]);
+    assert_eq!(ids(&stranger), vec![&env, public.id]);
+    assert_eq!(client.search_posts(&symbol_short!("test"), &Some(stranger.clone()), &0, &10).len(), 1);
+    assert_eq!(client.get_user_posts(&author, &Some(follower.clone()), &0, &10).len(), 2);
+
+    // Only viewers in scope can interact with a post or read its comments
+    let comment = client.add_comment(&for_followers.id, &follower, &text(&env, "Seen it"), &None);
+    let (post_target, comment_target) = (Target::Post(for_followers.id), Target::Comment(comment.id));
+    assert!(client.try_add_comment(&for_followers.id, &stranger, &text(&env, "Hi"), &None).is_err());
+    let commitment = BytesN::from_array(&env, &[7; 32]);
+    assert!(client.try_add_anonymous_comment(&for_followers.id, &commitment, &text(&env, "Hi"), &None).is_err());
+    assert!(client.try_react(&post_target, &stranger, &symbol_short!("like")).is_err());
+    assert!(client.try_like_post(&for_followers.id, &stranger).is_err());
+    assert_eq!(client.unlike_post(&for_followers.id, &stranger), None);
+    assert!(client.like_post(&for_followers.id, &follower).is_some());
+    assert!(client.try_react(&comment_target, &stranger, &symbol_short!("like")).is_err());
+    assert!(client.try_report(&comment_target, &stranger, &symbol_short!("spam")).is_err());
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    StellarAssetClient::new(&env, &token).mint(&stranger, &100);
+    assert!(client.try_tip_post(&for_followers.id, &stranger, &token, &100).is_err());
+    client.react(&comment_target, &follower, &symbol_short!("like"));
+
+    assert_eq!(client.get_comment(&comment.id, &Some(stranger.clone())), None);
+    assert!(client.get_comment(&comment.id, &Some(follower.clone())).is_some());
+    assert!(client.get_post_comments(&for_followers.id, &Some(stranger.clone()), &None, &1, &None, &10).0.is_empty());
+    assert_eq!(client.get_post_comments(&for_followers.id, &Some(follower), &None, &1, &None, &10).0.len(), 1);
+    // A public post id does not open a comment thread on a scoped post
+    let thread = Some(comment.id);
+    assert!(client.try_get_post_comments(&public.id, &Some(stranger), &thread, &1, &None, &10).is_err());
+
+    // Ciphertext-only posts cannot be edited into plaintext or reposted
+    assert!(client.try_edit_post(&for_followers.id, &author, &None, &text(&env, "Leaked")).is_err());
+    assert!(client.try_repost(&for_matches.id, &partner).is_err());
+}
+
+#[test]
+fn test_post_pages() {
+    let env = Env::default();
+    let client = setup(&env);
+    let author = Address::generate(&env);
+    let tag = symbol_short!("test");
+
+    let first = client.create_post(&author, &text(&env, "Post 1"), &vec![&env, tag.clone()]);
+    let second = client.create_post(&author, &text(&env, "Post 2"), &vec![&env, tag.clone()]);
+    let third = client.create_post(&author, &text(&env, "Post 3"), &vec![&env, tag.clone()]);
+
+    let page = client.search_posts(&tag, &None, &0, &2);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get_unchecked(0).id, first.id);
+    assert_eq!(page.get_unchecked(1).id, second.id);
+    let page = client.get_user_posts(&author, &None, &2, &2);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get_unchecked(0).id, third.id);
+    assert_eq!(client.search_posts(&tag, &None, &3, &2).len(), 0);
+}
+
+#[test]
+fn test_likes_reported_to_analytics() {
+    let env = Env::default();
+    let client = setup(&env);
+    let analytics = MockAnalyticsClient::new(&env, &env.register_contract(None, MockAnalytics));
+    client.set_analytics_contract(&analytics.address);
+    let author = Address::generate(&env);
+    let blocked = Address::generate(&env);
+
+    let post = client.create_post(&author, &text(&env, "Like me"), &vec![&env, symbol_short!("test")]);
+    let fan = Address::generate(&env);
+    client.react(&Target::Post(post.id), &fan, &symbol_short!("love"));
+    assert_eq!(analytics.likes_received(&author), 0);
+    client.like_post(&post.id, &fan);
+    assert_eq!(analytics.likes_received(&author), 1);
+
+    // Only the first like from a user is reported
+    client.unlike_post(&post.id, &fan);
+    client.like_post(&post.id, &fan);
+    assert_eq!(analytics.likes_received(&author), 1);
+
+    // A failing analytics call does not block the like
+    analytics.block(&blocked);
+    let post = client.create_post(&blocked, &text(&env, "Like me too"), &vec![&env, symbol_short!("test")]);
+    assert_eq!(client.like_post(&post.id, &Address::generate(&env)).unwrap().likes, 1);
+    assert_eq!(analytics.likes_received(&blocked), 0);
+
+    // Engagement is tracked for at most ENGAGED_CAP users; later likes still count on the post
+    let post = client.create_post(&author, &text(&env, "Popular"), &vec![&env, symbol_short!("test")]);
+    let before = analytics.likes_received(&author);
+    for _ in 0..ENGAGED_CAP + 1 {
+        env.budget().reset_default();
+        client.like_post(&post.id, &Address::generate(&env));
+    }
+    assert_eq!(client.get_post(&post.id, &None).unwrap().likes, ENGAGED_CAP + 1);
+    assert_eq!(analytics.likes_received(&author), before + ENGAGED_CAP);
+    let engaged = env.as_contract(&client.address, || {
+        env.storage().persistent().get::<_, Map<Address, bool>>(&DataKey::Engaged(post.id)).unwrap()
+    });
+    assert_eq!(engaged.len(), ENGAGED_CAP);
+}
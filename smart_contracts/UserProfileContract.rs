@@ -1,7 +1,48 @@
 #![no_std]
-use soroban_sdk::{contractimpl, Address, Env, Symbol, Vec, String};
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, vec, Address,
+    BytesN, Env, String, Symbol, Vec,
+};
 
+// Bumped whenever stored data needs migrating after an upgrade
+const SCHEMA_VERSION: u32 = 1;
+
+// A viewer counts once per profile in each period
+const VIEW_PERIOD: u64 = 24 * 60 * 60;
+// Ledgers a recorded view is kept for, at about five seconds a ledger; long enough to cover its period
+const VIEW_TTL_LEDGERS: u32 = (VIEW_PERIOD / 5) as u32;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ProfileError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    Paused = 3,
+    UnknownSchemaVersion = 4,
+    UnauthorizedReporter = 5,
+    FeatureLocked = 6,
+    ProfileNotFound = 7,
+    InvalidMatch = 8,
+    InvalidEvent = 9,
+}
+
+#[contracttype]
 #[derive(Clone)]
+pub enum DataKey {
+    Admin,
+    Paused,
+    SchemaVersion,
+    Profile(Address),
+    PremiumContract,
+    Reporters,
+    Analytics(Address),
+    MatchProposal(Address, Address), // (proposer, other)
+    ProfileViewed(Address, Address), // (user, viewer) -> period of the last counted view; temporary
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct UserProfile {
     pub name: String,
     pub birthdate: String,
@@ -10,10 +51,81 @@ pub struct UserProfile {
     pub profession: String,
 }
 
+// Profile activity. Views and matches are counted here; likes and messages are reported by
+// PostContract and MessagingContract.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AnalyticsEvent {
+    ProfileView,
+    LikeReceived,
+    MessageReceived,
+    MatchProposed,
+    MatchMade,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProfileAnalytics {
+    pub profile_views: u64,
+    pub likes_received: u64,
+    pub messages_received: u64,
+    pub matches_proposed: u64,
+    pub matches_made: u64,
+    pub match_rate_bps: u32, // matches_made / matches_proposed in basis points
+}
+
+// The subset of PremiumContract used to gate the analytics feature
+#[contractclient(name = "PremiumClient")]
+pub trait PremiumInterface {
+    fn has_feature_access(env: Env, user: Address, feature_name: Symbol) -> bool;
+}
+
+#[contract]
 pub struct UserProfileContract;
 
 #[contractimpl]
 impl UserProfileContract {
+    pub fn initialize(env: Env, admin: Address) {
+        if env.storage().instance().has(&DataKey::Admin) {
+            panic_with_error!(&env, ProfileError::AlreadyInitialized);
+        }
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+    }
+
+    // Pause or resume profile writes. Reads keep working.
+    pub fn set_paused(env: Env, paused: bool) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    // Swap in new code, keeping profiles and analytics; call `migrate` after a schema bump
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
+        Self::require_admin(&env);
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+    }
+
+    // Bring stored data up to the current schema version
+    pub fn migrate(env: Env) -> u32 {
+        Self::require_admin(&env);
+        let version = Self::get_schema_version(env.clone());
+        if version > SCHEMA_VERSION {
+            panic_with_error!(&env, ProfileError::UnknownSchemaVersion);
+        }
+        env.storage().instance().set(&DataKey::SchemaVersion, &SCHEMA_VERSION);
+        SCHEMA_VERSION
+    }
+
+    pub fn get_schema_version(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::SchemaVersion).unwrap_or(0)
+    }
+
     pub fn create_profile(
         env: Env,
         user: Address,
@@ -23,8 +135,10 @@ impl UserProfileContract {
         country: String,
         profession: String,
     ) {
+        Self::require_not_paused(&env);
+        user.require_auth();
         let profile = UserProfile { name, birthdate, gender, country, profession };
-        env.storage().set(&user, &profile);
+        env.storage().persistent().set(&DataKey::Profile(user), &profile);
     }
 
     pub fn update_profile(
@@ -36,11 +150,156 @@ impl UserProfileContract {
         country: String,
         profession: String,
     ) {
+        Self::require_not_paused(&env);
+        user.require_auth();
         let profile = UserProfile { name, birthdate, gender, country, profession };
-        env.storage().set(&user, &profile);
+        env.storage().persistent().set(&DataKey::Profile(user), &profile);
     }
 
     pub fn get_profile(env: Env, user: Address) -> Option<UserProfile> {
-        env.storage().get(&user)
+        env.storage().persistent().get(&DataKey::Profile(user))
+    }
+
+    // Read another user's profile, counting the view toward their analytics once per viewer per VIEW_PERIOD
+    pub fn view_profile(env: Env, viewer: Address, user: Address) -> Option<UserProfile> {
+        viewer.require_auth();
+        let profile = Self::get_profile(env.clone(), user.clone());
+        if profile.is_some() && viewer != user && !Self::is_paused(env.clone()) {
+            let key = DataKey::ProfileViewed(user.clone(), viewer);
+            let period = env.ledger().timestamp() / VIEW_PERIOD;
+            if env.storage().temporary().get::<_, u64>(&key) != Some(period) {
+                env.storage().temporary().set(&key, &period);
+                env.storage().temporary().extend_ttl(&key, VIEW_TTL_LEDGERS, VIEW_TTL_LEDGERS);
+                Self::count_event(&env, &user, AnalyticsEvent::ProfileView);
+            }
+        }
+        profile
+    }
+
+    // Propose a match with another profile. The two are matched once both have proposed.
+    pub fn propose_match(env: Env, user: Address, other: Address) -> bool {
+        Self::require_not_paused(&env);
+        user.require_auth();
+        if user == other {
+            panic_with_error!(&env, ProfileError::InvalidMatch);
+        }
+        if !env.storage().persistent().has(&DataKey::Profile(user.clone()))
+            || !env.storage().persistent().has(&DataKey::Profile(other.clone()))
+        {
+            panic_with_error!(&env, ProfileError::ProfileNotFound);
+        }
+
+        let key = DataKey::MatchProposal(user.clone(), other.clone());
+        if env.storage().persistent().has(&key) {
+            return Self::is_matched(env, user, other);
+        }
+        env.storage().persistent().set(&key, &true);
+        Self::count_event(&env, &user, AnalyticsEvent::MatchProposed);
+
+        let matched = Self::is_matched(env.clone(), user.clone(), other.clone());
+        if matched {
+            Self::count_event(&env, &user, AnalyticsEvent::MatchMade);
+            Self::count_event(&env, &other, AnalyticsEvent::MatchMade);
+            env.events().publish((symbol_short!("matched"), user), other);
+        }
+        matched
+    }
+
+    // Whether both users have proposed a match with each other
+    pub fn is_matched(env: Env, user: Address, other: Address) -> bool {
+        env.storage().persistent().has(&DataKey::MatchProposal(user.clone(), other.clone()))
+            && env.storage().persistent().has(&DataKey::MatchProposal(other, user))
+    }
+
+    pub fn set_premium_contract(env: Env, premium_contract: Address) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::PremiumContract, &premium_contract);
+    }
+
+    // Allow a contract to report analytics events
+    pub fn add_reporter(env: Env, reporter: Address) {
+        Self::require_admin(&env);
+        let mut reporters = Self::get_reporters(env.clone());
+        if !reporters.contains(&reporter) {
+            reporters.push_back(reporter);
+            env.storage().instance().set(&DataKey::Reporters, &reporters);
+        }
+    }
+
+    pub fn remove_reporter(env: Env, reporter: Address) {
+        Self::require_admin(&env);
+        let mut reporters = Self::get_reporters(env.clone());
+        if let Some(index) = reporters.first_index_of(&reporter) {
+            reporters.remove(index);
+            env.storage().instance().set(&DataKey::Reporters, &reporters);
+        }
+    }
+
+    pub fn get_reporters(env: Env) -> Vec<Address> {
+        env.storage().instance().get(&DataKey::Reporters).unwrap_or(vec![&env])
+    }
+
+    // Count a like or message against a user's profile. Only registered reporters may call this;
+    // views and matches are counted by this contract alone.
+    pub fn record_event(env: Env, reporter: Address, user: Address, event: AnalyticsEvent) {
+        Self::require_not_paused(&env);
+        reporter.require_auth();
+        if !Self::get_reporters(env.clone()).contains(&reporter) {
+            panic_with_error!(&env, ProfileError::UnauthorizedReporter);
+        }
+        if !matches!(event, AnalyticsEvent::LikeReceived | AnalyticsEvent::MessageReceived) {
+            panic_with_error!(&env, ProfileError::InvalidEvent);
+        }
+        Self::count_event(&env, &user, event);
+    }
+
+    // Profile insights, readable only by the profile owner with the analytics premium feature
+    pub fn get_analytics(env: Env, user: Address) -> ProfileAnalytics {
+        user.require_auth();
+        let premium: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PremiumContract)
+            .unwrap_or_else(|| panic_with_error!(&env, ProfileError::NotInitialized));
+        if !PremiumClient::new(&env, &premium).has_feature_access(&user, &symbol_short!("analytics")) {
+            panic_with_error!(&env, ProfileError::FeatureLocked);
+        }
+        env.storage().persistent().get(&DataKey::Analytics(user)).unwrap_or_default()
+    }
+
+    fn require_admin(env: &Env) {
+        let admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .unwrap_or_else(|| panic_with_error!(env, ProfileError::NotInitialized));
+        admin.require_auth();
+    }
+
+    fn require_not_paused(env: &Env) {
+        if Self::is_paused(env.clone()) {
+            panic_with_error!(env, ProfileError::Paused);
+        }
+    }
+
+    fn count_event(env: &Env, user: &Address, event: AnalyticsEvent) {
+        let key = DataKey::Analytics(user.clone());
+        let mut analytics: ProfileAnalytics = env.storage().persistent().get(&key).unwrap_or_default();
+        match event {
+            AnalyticsEvent::ProfileView => analytics.profile_views += 1,
+            AnalyticsEvent::LikeReceived => analytics.likes_received += 1,
+            AnalyticsEvent::MessageReceived => analytics.messages_received += 1,
+            AnalyticsEvent::MatchProposed => analytics.matches_proposed += 1,
+            AnalyticsEvent::MatchMade => analytics.matches_made += 1,
+        }
+        // Every match follows the user's own proposal, so the rate never passes 100%
+        if let Some(rate) = (analytics.matches_made * 10_000).checked_div(analytics.matches_proposed) {
+            analytics.match_rate_bps = rate as u32;
+        }
+        env.storage().persistent().set(&key, &analytics);
     }
 }
+
+#[cfg(test)]
+#[path = "UserProfileContract_test.rs"]
+mod test;
@@ -1,234 +1,193 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{Address, Env, Symbol};
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::{Address, Env, String, Symbol};
+
+fn setup(env: &Env) -> UserProfileContractClient<'_> {
+    env.mock_all_auths();
+    let client = UserProfileContractClient::new(env, &env.register_contract(None, UserProfileContract));
+    client.initialize(&Address::generate(env));
+    client
+}
+
+fn text(env: &Env, value: &str) -> String {
+    String::from_str(env, value)
+}
+
+fn create(env: &Env, client: &UserProfileContractClient, user: &Address, name: &str, profession: &str) {
+    client.create_profile(
+        user,
+        &text(env, name),
+        &text(env, "1999-01-01"),
+        &text(env, "female"),
+        &text(env, "TR"),
+        &text(env, profession),
+    );
+}
 
 #[test]
 fn test_create_profile() {
     let env = Env::default();
+    let client = setup(&env);
     let user = Address::generate(&env);
-    
-    let profile = UserProfileContract::create_profile(
-        &env,
-        user.clone(),
-        symbol_short!("Alice"),
-        25,
-        symbol_short!("Software Engineer"),
-        symbol_short!("I love coding and blockchain technology"),
-        vec![&env, symbol_short!("coding"), symbol_short!("blockchain"), symbol_short!("music")]
-    );
-    
-    assert_eq!(profile.user, user);
-    assert_eq!(profile.display_name, symbol_short!("Alice"));
-    assert_eq!(profile.age, 25);
-    assert_eq!(profile.occupation, symbol_short!("Software Engineer"));
-    assert_eq!(profile.bio, symbol_short!("I love coding and blockchain technology"));
-    assert_eq!(profile.interests.len(), 3);
+
+    create(&env, &client, &user, "Alice", "Software Engineer");
+
+    let profile = client.get_profile(&user).unwrap();
+    assert_eq!(profile.name, text(&env, "Alice"));
+    assert_eq!(profile.profession, text(&env, "Software Engineer"));
+    assert_eq!(profile.country, text(&env, "TR"));
 }
 
 #[test]
 fn test_update_profile() {
     let env = Env::default();
+    let client = setup(&env);
     let user = Address::generate(&env);
-    
-    // Create initial profile
-    UserProfileContract::create_profile(
-        &env,
-        user.clone(),
-        symbol_short!("Alice"),
-        25,
-        symbol_short!("Software Engineer"),
-        symbol_short!("I love coding"),
-        vec![&env, symbol_short!("coding")]
-    );
-    
-    // Update profile
-    let updated_profile = UserProfileContract::update_profile(
-        &env,
-        user.clone(),
-        symbol_short!("Alice Smith"),
-        26,
-        symbol_short!("Senior Software Engineer"),
-        symbol_short!("I love coding and blockchain technology"),
-        vec![&env, symbol_short!("coding"), symbol_short!("blockchain")]
+    create(&env, &client, &user, "Alice", "Software Engineer");
+
+    client.update_profile(
+        &user,
+        &text(&env, "Alice Smith"),
+        &text(&env, "1999-01-01"),
+        &text(&env, "female"),
+        &text(&env, "TR"),
+        &text(&env, "Senior Software Engineer"),
     );
-    
-    assert_eq!(updated_profile.display_name, symbol_short!("Alice Smith"));
-    assert_eq!(updated_profile.age, 26);
-    assert_eq!(updated_profile.occupation, symbol_short!("Senior Software Engineer"));
-    assert_eq!(updated_profile.interests.len(), 2);
+
+    let profile = client.get_profile(&user).unwrap();
+    assert_eq!(profile.name, text(&env, "Alice Smith"));
+    assert_eq!(profile.profession, text(&env, "Senior Software Engineer"));
 }
 
 #[test]
 fn test_get_profile() {
     let env = Env::default();
-    let user = Address::generate(&env);
-    
-    let created_profile = UserProfileContract::create_profile(
-        &env,
-        user.clone(),
-        symbol_short!("Bob"),
-        30,
-        symbol_short!("Designer"),
-        symbol_short!("Creative designer"),
-        vec![&env, symbol_short!("design"), symbol_short!("art")]
-    );
-    
-    let retrieved_profile = UserProfileContract::get_profile(&env, user.clone()).unwrap();
-    
-    assert_eq!(retrieved_profile.user, created_profile.user);
-    assert_eq!(retrieved_profile.display_name, created_profile.display_name);
-    assert_eq!(retrieved_profile.age, created_profile.age);
-    assert_eq!(retrieved_profile.occupation, created_profile.occupation);
-    assert_eq!(retrieved_profile.bio, created_profile.bio);
-    assert_eq!(retrieved_profile.interests.len(), created_profile.interests.len());
-}
+    let client = setup(&env);
+    let (alice, bob) = (Address::generate(&env), Address::generate(&env));
+    create(&env, &client, &alice, "Alice", "Engineer");
+    create(&env, &client, &bob, "Bob", "Designer");
 
-#[test]
-fn test_get_nonexistent_profile() {
-    let env = Env::default();
-    let user = Address::generate(&env);
-    
-    let profile = UserProfileContract::get_profile(&env, user);
-    assert!(profile.is_none());
+    assert_eq!(client.get_profile(&alice).unwrap().name, text(&env, "Alice"));
+    assert_eq!(client.get_profile(&bob).unwrap().profession, text(&env, "Designer"));
 }
 
 #[test]
-fn test_delete_profile() {
+fn test_profile_writes_need_owner_auth() {
     let env = Env::default();
+    let client = UserProfileContractClient::new(&env, &env.register_contract(None, UserProfileContract));
     let user = Address::generate(&env);
-    
-    // Create profile
-    UserProfileContract::create_profile(
-        &env,
-        user.clone(),
-        symbol_short!("Charlie"),
-        28,
-        symbol_short!("Artist"),
-        symbol_short!("Passionate artist"),
-        vec![&env, symbol_short!("art"), symbol_short!("painting")]
-    );
-    
-    // Verify profile exists
-    assert!(UserProfileContract::get_profile(&env, user.clone()).is_some());
-    
-    // Delete profile
-    UserProfileContract::delete_profile(&env, user.clone());
-    
-    // Verify profile is deleted
-    assert!(UserProfileContract::get_profile(&env, user).is_none());
-}
+    let name = text(&env, "Alice");
 
-#[test]
-fn test_search_profiles() {
-    let env = Env::default();
-    
-    // Create multiple profiles
-    let user1 = Address::generate(&env);
-    let user2 = Address::generate(&env);
-    let user3 = Address::generate(&env);
-    
-    UserProfileContract::create_profile(
-        &env,
-        user1.clone(),
-        symbol_short!("Alice"),
-        25,
-        symbol_short!("Software Engineer"),
-        symbol_short!("I love coding"),
-        vec![&env, symbol_short!("coding"), symbol_short!("blockchain")]
-    );
-    
-    UserProfileContract::create_profile(
-        &env,
-        user2.clone(),
-        symbol_short!("Bob"),
-        30,
-        symbol_short!("Designer"),
-        symbol_short!("Creative designer"),
-        vec![&env, symbol_short!("design"), symbol_short!("art")]
-    );
-    
-    UserProfileContract::create_profile(
-        &env,
-        user3.clone(),
-        symbol_short!("Charlie"),
-        28,
-        symbol_short!("Artist"),
-        symbol_short!("Passionate artist"),
-        vec![&env, symbol_short!("art"), symbol_short!("painting")]
-    );
-    
-    // Search for profiles with "art" interest
-    let art_profiles = UserProfileContract::search_profiles(&env, symbol_short!("art"));
-    assert_eq!(art_profiles.len(), 2); // Bob and Charlie have art interests
-    
-    // Search for profiles with "coding" interest
-    let coding_profiles = UserProfileContract::search_profiles(&env, symbol_short!("coding"));
-    assert_eq!(coding_profiles.len(), 1); // Only Alice has coding interest
+    assert!(client.try_create_profile(&user, &name, &name, &name, &name, &name).is_err());
+    assert!(client.try_update_profile(&user, &name, &name, &name, &name, &name).is_err());
+    assert!(client.get_profile(&user).is_none());
 }
 
 #[test]
-fn test_get_all_profiles() {
+fn test_get_nonexistent_profile() {
     let env = Env::default();
-    
-    // Create multiple profiles
-    let user1 = Address::generate(&env);
-    let user2 = Address::generate(&env);
-    
-    UserProfileContract::create_profile(
-        &env,
-        user1.clone(),
-        symbol_short!("Alice"),
-        25,
-        symbol_short!("Engineer"),
-        symbol_short!("Tech enthusiast"),
-        vec![&env, symbol_short!("tech")]
-    );
-    
-    UserProfileContract::create_profile(
-        &env,
-        user2.clone(),
-        symbol_short!("Bob"),
-        30,
-        symbol_short!("Designer"),
-        symbol_short!("Creative person"),
-        vec![&env, symbol_short!("design")]
-    );
-    
-    let all_profiles = UserProfileContract::get_all_profiles(&env);
-    assert_eq!(all_profiles.len(), 2);
-    
-    // Verify both profiles are in the list
-    let user1_found = all_profiles.iter().any(|p| p.user == user1);
-    let user2_found = all_profiles.iter().any(|p| p.user == user2);
-    
-    assert!(user1_found);
-    assert!(user2_found);
+    let client = setup(&env);
+
+    assert!(client.get_profile(&Address::generate(&env)).is_none());
 }
 
-#[test]
-fn test_profile_verification() {
-    let env = Env::default();
-    let user = Address::generate(&env);
-    
-    // Create unverified profile
-    let profile = UserProfileContract::create_profile(
-        &env,
-        user.clone(),
-        symbol_short!("Alice"),
-        25,
-        symbol_short!("Engineer"),
-        symbol_short!("Tech enthusiast"),
-        vec![&env, symbol_short!("tech")]
-    );
-    
-    assert!(!profile.verified);
-    
-    // Verify profile
-    let verified_profile = UserProfileContract::verify_profile(&env, user.clone());
-    assert!(verified_profile.verified);
-    
-    // Check that the profile is still verified when retrieved
-    let retrieved_profile = UserProfileContract::get_profile(&env, user).unwrap();
-    assert!(retrieved_profile.verified);
-} 
\ No newline at end of file
+mod analytics {
+    use super::*;
+    use soroban_sdk::{contract, contractimpl};
+
+    // Stand-in for PremiumContract that grants every feature to a single address
+    #[contract]
+    pub struct MockPremium;
+
+    #[contractimpl]
+    impl MockPremium {
+        pub fn set_premium_user(env: Env, user: Address) {
+            env.storage().instance().set(&0u32, &user);
+        }
+
+        pub fn has_feature_access(env: Env, user: Address, _feature_name: Symbol) -> bool {
+            env.storage().instance().get::<_, Address>(&0u32) == Some(user)
+        }
+    }
+
+    fn setup_analytics(env: &Env) -> (UserProfileContractClient<'_>, MockPremiumClient<'_>, Address) {
+        env.mock_all_auths();
+        let premium = MockPremiumClient::new(env, &env.register_contract(None, MockPremium));
+        let client = UserProfileContractClient::new(env, &env.register_contract(None, UserProfileContract));
+        let reporter = Address::generate(env);
+        client.initialize(&Address::generate(env));
+        client.set_premium_contract(&premium.address);
+        client.add_reporter(&reporter);
+        (client, premium, reporter)
+    }
+
+    #[test]
+    fn test_record_and_query_analytics() {
+        let env = Env::default();
+        let (client, premium, reporter) = setup_analytics(&env);
+        let user = Address::generate(&env);
+        premium.set_premium_user(&user);
+
+        client.record_event(&reporter, &user, &AnalyticsEvent::MessageReceived);
+        client.record_event(&reporter, &user, &AnalyticsEvent::LikeReceived);
+        client.record_event(&reporter, &user, &AnalyticsEvent::LikeReceived);
+
+        // Views and matches are counted by the contract itself
+        for event in [AnalyticsEvent::ProfileView, AnalyticsEvent::MatchProposed, AnalyticsEvent::MatchMade] {
+            assert_eq!(client.try_record_event(&reporter, &user, &event), Err(Ok(ProfileError::InvalidEvent.into())));
+        }
+
+        let analytics = client.get_analytics(&user);
+        assert_eq!(analytics.profile_views, 0);
+        assert_eq!(analytics.messages_received, 1);
+        assert_eq!(analytics.likes_received, 2);
+        assert_eq!(analytics.matches_made, 0);
+    }
+
+    #[test]
+    fn test_analytics_requires_feature_and_reporter() {
+        let env = Env::default();
+        let (client, _, _) = setup_analytics(&env);
+        let user = Address::generate(&env);
+
+        assert!(client.try_get_analytics(&user).is_err());
+        assert!(client.try_record_event(&Address::generate(&env), &user, &AnalyticsEvent::LikeReceived).is_err());
+    }
+
+    #[test]
+    fn test_views_and_matches_counted() {
+        let env = Env::default();
+        let (client, premium, _) = setup_analytics(&env);
+        let (alice, bob, carol) = (Address::generate(&env), Address::generate(&env), Address::generate(&env));
+        create(&env, &client, &alice, "Alice", "Engineer");
+        create(&env, &client, &bob, "Bob", "Designer");
+        create(&env, &client, &carol, "Carol", "Writer");
+        premium.set_premium_user(&alice);
+
+        // Owners looking at their own profile do not count, and a viewer counts once a day
+        assert_eq!(client.view_profile(&bob, &alice).unwrap().name, text(&env, "Alice"));
+        client.view_profile(&bob, &alice);
+        client.view_profile(&alice, &alice);
+        assert_eq!(client.get_analytics(&alice).profile_views, 1);
+        env.ledger().with_mut(|li| li.timestamp += VIEW_PERIOD);
+        client.view_profile(&bob, &alice);
+        client.view_profile(&carol, &alice);
+
+        assert!(!client.propose_match(&alice, &bob));
+        assert!(!client.propose_match(&alice, &bob));
+        assert!(!client.is_matched(&alice, &bob));
+        assert!(client.propose_match(&bob, &alice));
+        assert!(client.is_matched(&alice, &bob));
+        assert!(!client.propose_match(&alice, &carol));
+        assert!(client.try_propose_match(&alice, &alice).is_err());
+        assert!(client.try_propose_match(&alice, &Address::generate(&env)).is_err());
+
+        let analytics = client.get_analytics(&alice);
+        assert_eq!(analytics.profile_views, 3);
+        assert_eq!(analytics.matches_proposed, 2);
+        assert_eq!(analytics.matches_made, 1);
+        assert_eq!(analytics.match_rate_bps, 5_000);
+    }
+}